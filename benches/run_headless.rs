@@ -0,0 +1,43 @@
+//! Measures how many clock cycles per second `Computer::run_headless` can execute.
+//! Run with `cargo bench --bench run_headless`.
+
+use std::{hint::black_box, time::Instant};
+
+use rusty_man_computer::{Computer, ComputerConfig, InputSource};
+
+/// Counts down from 999 to -1, outputting every value, which takes 5000 cycles
+const COUNTDOWN: &str = "
+loop LDA count
+     OUT
+     SUB one
+     STA count
+     BRP loop
+     HLT
+count DAT 999
+one   DAT 1
+";
+
+fn main() {
+    let config = ComputerConfig {
+        quiet: true,
+        input_source: InputSource::Vector(Vec::new()),
+        ..ComputerConfig::default()
+    };
+    let mut computer = Computer::from_assembly(COUNTDOWN, config).unwrap();
+    let program = computer.snapshot();
+    let runs = 2000;
+    let start = Instant::now();
+    for _ in 0..runs {
+        computer.reset();
+        computer.restore(program.clone());
+        black_box(computer.run_headless()).unwrap();
+    }
+    let elapsed = start.elapsed();
+    let cycles = computer.execution_stats().total_cycles * runs;
+    println!(
+        "{} cycles in {:.2?} ({:.1} million cycles per second)",
+        cycles,
+        elapsed,
+        cycles as f64 / elapsed.as_secs_f64() / 1e6
+    );
+}
@@ -0,0 +1,1294 @@
+use std::{collections::HashMap, error::Error, fmt};
+
+use crate::{instruction::encode_for, Architecture};
+
+/// A problem with a line of assembly that stops the program from being assembled.
+/// Line numbers start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssemblerError {
+    /// The line has a label but nothing after it
+    MissingOpcode {
+        line: usize,
+    },
+    InvalidOpcode {
+        line: usize,
+        opcode: String,
+    },
+    /// The operand isn't a number or label, or is out of range for the instruction
+    InvalidOperand {
+        line: usize,
+        operand: String,
+    },
+    UnexpectedToken {
+        line: usize,
+        token: String,
+    },
+    /// A label or `EQU` constant is defined on `first_line` and again on `line`
+    DuplicateLabel {
+        line: usize,
+        label: String,
+        first_line: usize,
+    },
+    /// An operand on `line` names a label that isn't defined anywhere
+    UndefinedLabel {
+        line: usize,
+        label: String,
+    },
+    /// An instruction that uses a memory address, or an `ORG` or `EQU` directive, without an
+    /// operand
+    MissingOperand {
+        line: usize,
+    },
+    /// An `EQU` directive without a name for the constant
+    MissingLabel {
+        line: usize,
+    },
+    /// A label has the same name as an opcode, e.g. `OUT`
+    ReservedLabel {
+        line: usize,
+        label: String,
+    },
+    /// The program needs more memory cells than RAM has, so it couldn't all be loaded
+    ProgramTooLong {
+        excess_cells: usize,
+    },
+    /// Problems on more than one line, in the order they appear in the program
+    ParseErrors(Vec<AssemblerError>),
+}
+
+/// Turns the problems found in a pass over the program into a single error, unless there weren't
+/// any
+fn combine_errors(mut errors: Vec<AssemblerError>) -> Result<(), AssemblerError> {
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.remove(0)),
+        _ => Err(AssemblerError::ParseErrors(errors)),
+    }
+}
+
+impl fmt::Display for AssemblerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssemblerError::MissingOpcode { line } => {
+                write!(f, "Line {}: Expected an opcode after the label", line)
+            }
+            AssemblerError::InvalidOpcode { line, opcode } => {
+                write!(f, "Line {}: '{}' is not a valid opcode", line, opcode)
+            }
+            AssemblerError::InvalidOperand { line, operand } => {
+                write!(f, "Line {}: '{}' is not a valid operand", line, operand)
+            }
+            AssemblerError::UnexpectedToken { line, token } => {
+                write!(f, "Line {}: Unexpected '{}' after the operand", line, token)
+            }
+            AssemblerError::DuplicateLabel {
+                line,
+                label,
+                first_line,
+            } => {
+                write!(
+                    f,
+                    "Line {}: Label '{}' has already been defined on line {}",
+                    line, label, first_line
+                )
+            }
+            AssemblerError::UndefinedLabel { line, label } => {
+                write!(f, "Undefined label '{}' referenced on line {}", label, line)
+            }
+            AssemblerError::MissingOperand { line } => {
+                write!(f, "Line {}: Expected an operand after the opcode", line)
+            }
+            AssemblerError::MissingLabel { line } => {
+                write!(f, "Line {}: Expected a name before EQU", line)
+            }
+            AssemblerError::ReservedLabel { line, label } => {
+                write!(
+                    f,
+                    "Line {}: '{}' is a reserved opcode and cannot be used as a label",
+                    line, label
+                )
+            }
+            AssemblerError::ProgramTooLong { excess_cells } => {
+                write!(
+                    f,
+                    "The program is {} memory cells too long to fit in RAM",
+                    excess_cells
+                )
+            }
+            AssemblerError::ParseErrors(errors) => {
+                let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+        }
+    }
+}
+
+impl Error for AssemblerError {}
+
+impl AssemblerError {
+    /// The line the problem is on, unless it's about the whole program
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            AssemblerError::MissingOpcode { line }
+            | AssemblerError::InvalidOpcode { line, .. }
+            | AssemblerError::InvalidOperand { line, .. }
+            | AssemblerError::UnexpectedToken { line, .. }
+            | AssemblerError::DuplicateLabel { line, .. }
+            | AssemblerError::UndefinedLabel { line, .. }
+            | AssemblerError::MissingOperand { line }
+            | AssemblerError::MissingLabel { line }
+            | AssemblerError::ReservedLabel { line, .. } => Some(*line),
+            AssemblerError::ProgramTooLong { .. } | AssemblerError::ParseErrors(_) => None,
+        }
+    }
+
+    /// The part of the line that caused the problem, if there is one
+    pub fn token(&self) -> Option<&str> {
+        match self {
+            AssemblerError::InvalidOpcode { opcode: token, .. }
+            | AssemblerError::InvalidOperand { operand: token, .. }
+            | AssemblerError::UnexpectedToken { token, .. }
+            | AssemblerError::DuplicateLabel { label: token, .. }
+            | AssemblerError::UndefinedLabel { label: token, .. }
+            | AssemblerError::ReservedLabel { label: token, .. } => Some(token),
+            _ => None,
+        }
+    }
+
+    /// Formats the error along with the line of source code it's on, with the problem
+    /// underlined, e.g.
+    ///
+    /// ```text
+    /// Line 2: 'LDX' is not a valid opcode
+    ///   |
+    /// 2 |     LDX 12
+    ///   |     ^^^
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        if let AssemblerError::ParseErrors(errors) = self {
+            let rendered: Vec<String> = errors.iter().map(|error| error.render(source)).collect();
+            return rendered.join("\n\n");
+        }
+        let Some(line) = self.line() else {
+            return self.to_string();
+        };
+        let Some(text) = source.lines().nth(line - 1) else {
+            return self.to_string();
+        };
+        let gutter = " ".repeat(line.to_string().len());
+        let mut rendered = format!("{}\n{} |\n{} | {}", self, gutter, line, text);
+        let token_start = self.token().and_then(|token| find_token(text, token));
+        if let (Some(start), Some(token)) = (token_start, self.token()) {
+            // Keep any tabs so the carets line up with the token
+            let indent: String = text[..start]
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            rendered += &format!("\n{} | {}{}", gutter, indent, "^".repeat(token.len()));
+        }
+        rendered
+    }
+}
+
+/// Finds where `token` appears in a line of assembly as a whole word
+fn find_token(text: &str, token: &str) -> Option<usize> {
+    text.match_indices(token)
+        .map(|(start, _)| start)
+        .find(|&start| {
+            let before = text[..start].chars().next_back();
+            let after = text[start + token.len()..].chars().next();
+            before.is_none_or(char::is_whitespace) && after.is_none_or(char::is_whitespace)
+        })
+}
+
+/// Something in the assembly that is probably a mistake, but doesn't stop it being assembled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssemblerWarning {
+    /// An operand was given to an instruction that doesn't use one, e.g. `OUT 5`
+    IgnoredOperand { line: usize, opcode: String },
+}
+
+impl fmt::Display for AssemblerWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssemblerWarning::IgnoredOperand { line, opcode } => {
+                write!(
+                    f,
+                    "Line {}: {} doesn't use an operand, so it was ignored",
+                    line, opcode
+                )
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Opcode {
+    /// An instruction whose operand is a memory address, stored in the last two digits after
+    /// the opcode, e.g. `LDA` has opcode 5
+    WithAddress(i16),
+    /// An instruction that ignores its operand, so its address part is always the same, e.g.
+    /// `OUT` is always opcode 9 and address 2, which is `902`
+    Fixed(i16, usize),
+    /// `DAT` - The operand is stored directly in memory instead of being an instruction
+    Data,
+    /// `ORG` - Makes the next instruction go at the address given, leaving the cells in between
+    /// as zeroes. Nothing is stored for the directive itself.
+    Origin,
+    /// `EQU` - Defines the label as a constant, e.g. `COUNT EQU 10` makes `COUNT` mean 10
+    /// wherever it is used as an operand. Nothing is stored for the directive itself.
+    Equate,
+}
+
+/// Finds the opcode for a mnemonic, in any case, e.g. `add` or `Add`. Labels are still
+/// case-sensitive.
+fn parse_opcode(mnemonic: &str) -> Option<Opcode> {
+    match mnemonic.to_ascii_uppercase().as_str() {
+        "HLT" | "COB" => Some(Opcode::Fixed(0, 0)),
+        "ADD" => Some(Opcode::WithAddress(1)),
+        "SUB" => Some(Opcode::WithAddress(2)),
+        "STA" | "STO" => Some(Opcode::WithAddress(3)),
+        "LDA" => Some(Opcode::WithAddress(5)),
+        "BRA" => Some(Opcode::WithAddress(6)),
+        "BRZ" => Some(Opcode::WithAddress(7)),
+        "BRP" => Some(Opcode::WithAddress(8)),
+        "INP" => Some(Opcode::Fixed(9, 1)),
+        "OUT" => Some(Opcode::Fixed(9, 2)),
+        "INC" => Some(Opcode::Fixed(9, 21)),
+        "OTC" => Some(Opcode::Fixed(9, 22)),
+        // The non-standard MUL, DIV and AST instructions are stored as negative numbers
+        "MUL" => Some(Opcode::WithAddress(-1)),
+        "DIV" => Some(Opcode::WithAddress(-2)),
+        "AST" => Some(Opcode::WithAddress(-3)),
+        "DAT" => Some(Opcode::Data),
+        "ORG" => Some(Opcode::Origin),
+        "EQU" => Some(Opcode::Equate),
+        _ => None,
+    }
+}
+
+enum Operand<'a> {
+    Number(i16),
+    Label(&'a str),
+}
+
+impl fmt::Display for Operand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Number(number) => write!(f, "{}", number),
+            Operand::Label(label) => write!(f, "{}", label),
+        }
+    }
+}
+
+struct Instruction<'a> {
+    line: usize,
+    label: Option<&'a str>,
+    /// The opcode as it was written, e.g. `STO`
+    mnemonic: &'a str,
+    opcode: Opcode,
+    /// Only `DAT` can have more than one operand
+    operands: Vec<Operand<'a>>,
+}
+
+impl Instruction<'_> {
+    /// How many memory cells the instruction takes up
+    fn size(&self) -> usize {
+        match self.opcode {
+            Opcode::Origin | Opcode::Equate => 0,
+            // Each value after a DAT goes in its own cell, and a DAT on its own stores a zero
+            Opcode::Data => self.operands.len().max(1),
+            _ => 1,
+        }
+    }
+}
+
+/// Works out the ASCII code of a character literal, e.g. `'A'` is 65. `'\n'`, `'\t'`, `'\0'`,
+/// `'\\'` and `'\''` are escapes for characters that are hard to write otherwise.
+fn parse_char_literal(token: &str) -> Option<i16> {
+    let inner = token.strip_prefix('\'')?.strip_suffix('\'')?;
+    let character = match inner {
+        "\\n" => '\n',
+        "\\t" => '\t',
+        "\\0" => '\0',
+        "\\\\" => '\\',
+        "\\'" => '\'',
+        escape if escape.starts_with('\\') => return None,
+        _ => {
+            let mut chars = inner.chars();
+            let character = chars.next()?;
+            // Only one character can go in a cell
+            if chars.next().is_some() {
+                return None;
+            }
+            character
+        }
+    };
+    character.is_ascii().then_some(character as i16)
+}
+
+/// Parses a decimal number, or a hexadecimal or binary one written like `0x1F` or `0b101`.
+/// Any of them can have a sign in front.
+fn parse_number(token: &str) -> Option<i16> {
+    let (negative, digits) = match token.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, token.strip_prefix('+').unwrap_or(token)),
+    };
+    let (radix, digits) = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        (16, hex)
+    } else if let Some(binary) = digits
+        .strip_prefix("0b")
+        .or_else(|| digits.strip_prefix("0B"))
+    {
+        (2, binary)
+    } else {
+        (10, digits)
+    };
+    // from_str_radix would accept another sign after the prefix, like 0x-1
+    if !digits.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let magnitude = i16::from_str_radix(digits, radix).ok()?;
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+fn parse_operand(token: &str, line: usize) -> Result<Operand<'_>, AssemblerError> {
+    let invalid_operand = || AssemblerError::InvalidOperand {
+        line,
+        operand: token.to_string(),
+    };
+    if token.starts_with('\'') {
+        return parse_char_literal(token)
+            .map(Operand::Number)
+            .ok_or_else(invalid_operand);
+    }
+    if let Some(number) = parse_number(token) {
+        return Ok(Operand::Number(number));
+    }
+    // Anything that looks like it was meant to be a number can't be a label
+    let first_char = token.chars().next().unwrap_or_default();
+    if first_char.is_ascii_digit() || first_char == '-' || first_char == '+' {
+        return Err(invalid_operand());
+    }
+    Ok(Operand::Label(token))
+}
+
+/// Splits a line of assembly into tokens, leaving out any comment. Comments start with `//`, `;`
+/// or `#` and run until the end of the line. A character literal like `' '` or `'#'` stays as
+/// one token, even though it has a space or a comment marker in it.
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut token_start = None;
+    let mut in_literal = false;
+    let mut chars = text.char_indices();
+    while let Some((index, character)) = chars.next() {
+        if in_literal {
+            match character {
+                // The escaped character might be a quote, which doesn't end the literal
+                '\\' => {
+                    chars.next();
+                }
+                '\'' => in_literal = false,
+                _ => {}
+            }
+            continue;
+        }
+        let rest = &text[index..];
+        if rest.starts_with("//") || rest.starts_with(';') || rest.starts_with('#') {
+            break;
+        }
+        if character.is_whitespace() {
+            if let Some(start) = token_start.take() {
+                tokens.push(&text[start..index]);
+            }
+        } else if token_start.is_none() {
+            token_start = Some(index);
+            // Quotes in the middle of a token, e.g. in a label, don't start a literal
+            in_literal = character == '\'';
+        }
+    }
+    if let Some(start) = token_start {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+/// Parses a single line of assembly, returning `None` if it is blank or only has a comment
+fn parse_line(text: &str, line: usize) -> Result<Option<Instruction<'_>>, AssemblerError> {
+    let mut tokens = tokenize(text).into_iter().peekable();
+    let Some(first_token) = tokens.next() else {
+        return Ok(None);
+    };
+
+    // Two opcodes in a row means one of them was meant to be a label, e.g. `OUT LDA 5`
+    if let (Some(first_opcode), Some(&second_token)) = (parse_opcode(first_token), tokens.peek()) {
+        if parse_opcode(second_token).is_some() {
+            // In `BRA OUT`, the label is the operand rather than the start of the line
+            let takes_operand = !matches!(first_opcode, Opcode::Fixed(..));
+            let label = if takes_operand && tokens.clone().nth(1).is_none() {
+                second_token
+            } else {
+                first_token
+            };
+            return Err(AssemblerError::ReservedLabel {
+                line,
+                label: label.to_string(),
+            });
+        }
+    }
+
+    // Lines can optionally start with a label
+    let (label, opcode_token) = match parse_opcode(first_token) {
+        Some(_) => (None, first_token),
+        None => {
+            let opcode_token = tokens
+                .next()
+                .ok_or(AssemblerError::MissingOpcode { line })?;
+            (Some(first_token), opcode_token)
+        }
+    };
+    let opcode = parse_opcode(opcode_token).ok_or_else(|| {
+        // If a number follows the first token, the first token was meant to be the opcode
+        let meant_as_opcode = match opcode_token.parse::<i16>() {
+            Ok(_) => first_token,
+            Err(_) => opcode_token,
+        };
+        AssemblerError::InvalidOpcode {
+            line,
+            opcode: meant_as_opcode.to_string(),
+        }
+    })?;
+    let mut operands = Vec::new();
+    for token in tokens {
+        // DAT can store several values in a row, e.g. `table DAT 1 2 3`
+        if !operands.is_empty() && opcode != Opcode::Data {
+            return Err(AssemblerError::UnexpectedToken {
+                line,
+                token: token.to_string(),
+            });
+        }
+        operands.push(parse_operand(token, line)?);
+    }
+    // Leaving out the address would silently use address 0, which is almost always a mistake
+    if matches!(opcode, Opcode::WithAddress(_)) && operands.is_empty() {
+        return Err(AssemblerError::MissingOperand { line });
+    }
+    Ok(Some(Instruction {
+        line,
+        label,
+        mnemonic: opcode_token,
+        opcode,
+        operands,
+    }))
+}
+
+/// Works out the address of each instruction. Each one takes up the next memory cell, unless an
+/// `ORG` directive moves on to a later address, which can be given as an `EQU` constant.
+fn assign_addresses(
+    instructions: &[Instruction],
+    equates: &HashMap<&str, i16>,
+    architecture: Architecture,
+) -> Result<Vec<usize>, AssemblerError> {
+    let mut addresses = Vec::with_capacity(instructions.len());
+    let mut next_address = 0;
+    for instruction in instructions {
+        if instruction.opcode == Opcode::Origin {
+            let line = instruction.line;
+            let operand = instruction
+                .operands
+                .first()
+                .ok_or(AssemblerError::MissingOperand { line })?;
+            // Labels for addresses aren't known yet, so only constants can be used
+            let number = match operand {
+                Operand::Number(number) => Some(*number),
+                Operand::Label(label) => equates.get(label).copied(),
+            };
+            let origin = number
+                .and_then(|number| usize::try_from(number).ok())
+                // Going backwards would overwrite instructions that have already been placed
+                .filter(|&origin| (next_address..architecture.memory_size()).contains(&origin))
+                .ok_or_else(|| AssemblerError::InvalidOperand {
+                    line,
+                    operand: operand.to_string(),
+                })?;
+            // A label on the ORG line refers to the instruction after it
+            addresses.push(origin);
+            next_address = origin;
+        } else {
+            addresses.push(next_address);
+            next_address += instruction.size();
+        }
+    }
+    Ok(addresses)
+}
+
+/// Finds the constants defined with `EQU`
+fn generate_equate_table<'a>(
+    instructions: &[Instruction<'a>],
+) -> Result<HashMap<&'a str, i16>, AssemblerError> {
+    let mut equates = HashMap::new();
+    for instruction in instructions {
+        if instruction.opcode != Opcode::Equate {
+            continue;
+        }
+        let line = instruction.line;
+        let name = instruction
+            .label
+            .ok_or(AssemblerError::MissingLabel { line })?;
+        let value = match instruction.operands.first() {
+            None => return Err(AssemblerError::MissingOperand { line }),
+            Some(Operand::Number(number)) => *number,
+            Some(Operand::Label(label)) => {
+                return Err(AssemblerError::InvalidOperand {
+                    line,
+                    operand: label.to_string(),
+                })
+            }
+        };
+        if equates.insert(name, value).is_some() {
+            return Err(duplicate_label(instructions, name, line));
+        }
+    }
+    Ok(equates)
+}
+
+/// Describes `label` being defined twice, where one of the definitions is on `line`
+fn duplicate_label(instructions: &[Instruction], label: &str, line: usize) -> AssemblerError {
+    let other_line = instructions
+        .iter()
+        .find(|instruction| instruction.label == Some(label) && instruction.line != line)
+        .map_or(line, |instruction| instruction.line);
+    AssemblerError::DuplicateLabel {
+        line: line.max(other_line),
+        label: label.to_string(),
+        first_line: line.min(other_line),
+    }
+}
+
+fn generate_label_table<'a>(
+    instructions: &[Instruction<'a>],
+    addresses: &[usize],
+    equates: &HashMap<&str, i16>,
+) -> Result<HashMap<&'a str, usize>, AssemblerError> {
+    let mut labels = HashMap::new();
+    for (instruction, &address) in instructions.iter().zip(addresses) {
+        if instruction.opcode == Opcode::Equate {
+            continue;
+        }
+        if let Some(label) = instruction.label {
+            if equates.contains_key(label) || labels.insert(label, address).is_some() {
+                return Err(duplicate_label(instructions, label, instruction.line));
+            }
+        }
+    }
+    Ok(labels)
+}
+
+/// Works out the value of an operand. Names are looked up as `EQU` constants first, then as
+/// labels for addresses.
+fn resolve_operand(
+    operand: &Operand,
+    equates: &HashMap<&str, i16>,
+    labels: &HashMap<&str, usize>,
+    line: usize,
+) -> Result<i16, AssemblerError> {
+    match operand {
+        Operand::Number(number) => Ok(*number),
+        Operand::Label(label) => {
+            if let Some(&value) = equates.get(label) {
+                return Ok(value);
+            }
+            match labels.get(label) {
+                Some(&address) => Ok(address as i16),
+                None => Err(AssemblerError::UndefinedLabel {
+                    line,
+                    label: label.to_string(),
+                }),
+            }
+        }
+    }
+}
+
+fn generate_machine_code(
+    instructions: &[Instruction],
+    addresses: &[usize],
+    equates: &HashMap<&str, i16>,
+    labels: &HashMap<&str, usize>,
+    architecture: Architecture,
+) -> Result<Vec<i16>, AssemblerError> {
+    let mut machine_code = Vec::with_capacity(instructions.len());
+    let mut errors = Vec::new();
+    for (instruction, &address) in instructions.iter().zip(addresses) {
+        match instruction_cells(instruction, equates, labels, architecture) {
+            Ok(Some(cells)) => {
+                // Fill any gap left by an ORG directive with zeroes
+                machine_code.resize(address, 0);
+                machine_code.extend(cells);
+            }
+            // Directives don't take up a memory cell
+            Ok(None) => {}
+            // Carry on, so that every line with a problem is reported at once
+            Err(error) => errors.push(error),
+        }
+    }
+    combine_errors(errors)?;
+    Ok(machine_code)
+}
+
+/// Works out the memory cells that an instruction is stored as, or `None` for a directive
+fn instruction_cells(
+    instruction: &Instruction,
+    equates: &HashMap<&str, i16>,
+    labels: &HashMap<&str, usize>,
+    architecture: Architecture,
+) -> Result<Option<Vec<i16>>, AssemblerError> {
+    let line = instruction.line;
+    let operands = instruction
+        .operands
+        .iter()
+        .map(|operand| resolve_operand(operand, equates, labels, line))
+        .collect::<Result<Vec<i16>, AssemblerError>>()?;
+    let operand = operands.first().copied().unwrap_or(0);
+    // Report the operand as it was written, e.g. the name of a constant rather than its value
+    let invalid_operand = |index: usize| AssemblerError::InvalidOperand {
+        line,
+        operand: instruction.operands[index].to_string(),
+    };
+
+    let cells = match instruction.opcode {
+        Opcode::WithAddress(opcode) => {
+            let cell = usize::try_from(operand)
+                .ok()
+                .and_then(|address| encode_for(opcode, address, architecture))
+                .ok_or_else(|| invalid_operand(0))?;
+            vec![cell]
+        }
+        // INC and OTC don't fit when addresses only have one digit
+        Opcode::Fixed(opcode, address) => vec![encode_for(opcode, address, architecture)
+            .ok_or_else(|| AssemblerError::InvalidOpcode {
+                line,
+                opcode: instruction.mnemonic.to_string(),
+            })?],
+        Opcode::Data => {
+            if let Some(index) = operands
+                .iter()
+                .position(|value| !architecture.value_range().contains(value))
+            {
+                return Err(invalid_operand(index));
+            }
+            if operands.is_empty() {
+                vec![0]
+            } else {
+                operands
+            }
+        }
+        Opcode::Origin | Opcode::Equate => return Ok(None),
+    };
+    Ok(Some(cells))
+}
+
+fn find_warnings(instructions: &[Instruction]) -> Vec<AssemblerWarning> {
+    instructions
+        .iter()
+        .filter(|instruction| {
+            matches!(instruction.opcode, Opcode::Fixed(..)) && !instruction.operands.is_empty()
+        })
+        .map(|instruction| AssemblerWarning::IgnoredOperand {
+            line: instruction.line,
+            opcode: instruction.mnemonic.to_string(),
+        })
+        .collect()
+}
+
+/// Assembles Little Man Computer assembly into the values of each memory cell, starting at
+/// address 0. Each line can have an optional label, an opcode, and an optional operand, which is
+/// a number (in decimal, or hexadecimal or binary like `0x1F` or `0b101`), a character like `'A'`,
+/// or a label. Opcodes can be written in any case, but labels are case-sensitive.
+/// `DAT` can have several operands, which go in consecutive cells.
+/// Comments start with `//`, `;` or `#`. `ORG nn` makes the following instructions start at address `nn`,
+/// and `NAME EQU nn` defines a constant that can be used instead of a number.
+///
+/// ```
+/// use rusty_man_computer::assembler::assemble;
+///
+/// let source = "
+///     INP
+///     STA first // Remember the first number
+///     INP
+///     ADD first
+///     OUT
+///     HLT
+/// first DAT
+/// ";
+/// assert_eq!(assemble(source), Ok(vec![901, 306, 901, 106, 902, 0, 0]));
+/// ```
+pub fn assemble(source: &str) -> Result<Vec<i16>, AssemblerError> {
+    assemble_for(source, Architecture::CLASSIC)
+}
+
+/// Like `assemble`, but for a computer with a different architecture, so that addresses and
+/// values can have more or fewer digits
+pub fn assemble_for(source: &str, architecture: Architecture) -> Result<Vec<i16>, AssemblerError> {
+    assemble_with_warnings_for(source, architecture).map(|(machine_code, _)| machine_code)
+}
+
+/// Like `assemble`, but also returns anything that is probably a mistake, e.g. `INP 99`.
+/// If more than one line has a problem, they are all returned in `AssemblerError::ParseErrors`.
+pub fn assemble_with_warnings(
+    source: &str,
+) -> Result<(Vec<i16>, Vec<AssemblerWarning>), AssemblerError> {
+    assemble_with_warnings_for(source, Architecture::CLASSIC)
+}
+
+/// Like `assemble_with_warnings`, but for a computer with a different architecture
+pub fn assemble_with_warnings_for(
+    source: &str,
+    architecture: Architecture,
+) -> Result<(Vec<i16>, Vec<AssemblerWarning>), AssemblerError> {
+    let program = assemble_program(source, architecture)?;
+    Ok((program.machine_code, find_warnings(&program.instructions)))
+}
+
+/// Everything worked out while assembling a program
+struct Program<'a> {
+    instructions: Vec<Instruction<'a>>,
+    /// The address of each instruction
+    addresses: Vec<usize>,
+    equates: HashMap<&'a str, i16>,
+    labels: HashMap<&'a str, usize>,
+    machine_code: Vec<i16>,
+}
+
+fn assemble_program(
+    source: &str,
+    architecture: Architecture,
+) -> Result<Program<'_>, AssemblerError> {
+    let mut instructions = Vec::new();
+    let mut errors = Vec::new();
+    for (index, text) in source.lines().enumerate() {
+        match parse_line(text, index + 1) {
+            Ok(Some(instruction)) => instructions.push(instruction),
+            Ok(None) => {}
+            // Keep going, so that every line with a mistake is reported at once
+            Err(error) => errors.push(error),
+        }
+    }
+    combine_errors(errors)?;
+    let equates = generate_equate_table(&instructions)?;
+    let addresses = assign_addresses(&instructions, &equates, architecture)?;
+    let labels = generate_label_table(&instructions, &addresses, &equates)?;
+    let machine_code =
+        generate_machine_code(&instructions, &addresses, &equates, &labels, architecture)?;
+    let memory_size = architecture.memory_size();
+    if machine_code.len() > memory_size {
+        return Err(AssemblerError::ProgramTooLong {
+            excess_cells: machine_code.len() - memory_size,
+        });
+    }
+    Ok(Program {
+        instructions,
+        addresses,
+        equates,
+        labels,
+        machine_code,
+    })
+}
+
+/// Assembles a program and describes where each instruction ended up, for debugging. Each line
+/// has the address, label, instruction with its operands worked out, and the machine code it
+/// was stored as, e.g. `07  loop   BRA 03   -> 603`. A table of the labels and `EQU`
+/// constants comes after.
+pub fn listing(source: &str) -> Result<String, AssemblerError> {
+    listing_for(source, Architecture::CLASSIC)
+}
+
+/// Like `listing`, but for a computer with a different architecture
+pub fn listing_for(source: &str, architecture: Architecture) -> Result<String, AssemblerError> {
+    let program = assemble_program(source, architecture)?;
+    let address_width = architecture.address_digits() as usize;
+    let label_width = program
+        .instructions
+        .iter()
+        .filter_map(|instruction| instruction.label)
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<(String, String, Option<String>)> = program
+        .instructions
+        .iter()
+        .zip(&program.addresses)
+        .map(|(instruction, &address)| {
+            let mut code = instruction.mnemonic.to_string();
+            for operand in &instruction.operands {
+                // Assembling succeeded, so every operand can be resolved
+                let value =
+                    resolve_operand(operand, &program.equates, &program.labels, instruction.line)
+                        .unwrap_or_default();
+                code += &match instruction.opcode {
+                    Opcode::WithAddress(_) | Opcode::Origin => {
+                        format!(" {:0address_width$}", value)
+                    }
+                    _ => format!(" {}", value),
+                };
+            }
+            let cells = match instruction.opcode {
+                Opcode::Origin | Opcode::Equate => None,
+                _ => {
+                    let cells = &program.machine_code[address..address + instruction.size()];
+                    let cells: Vec<String> = cells.iter().map(ToString::to_string).collect();
+                    Some(cells.join(" "))
+                }
+            };
+            // Directives aren't stored anywhere, so they don't have an address
+            let address = match cells {
+                Some(_) => format!("{:0address_width$}", address),
+                None => String::new(),
+            };
+            let label = instruction.label.unwrap_or_default();
+            (address, format!("{:<label_width$}  {}", label, code), cells)
+        })
+        .collect();
+    let code_width = lines
+        .iter()
+        .map(|(_, code, _)| code.len())
+        .max()
+        .unwrap_or(0);
+    let mut listing = String::new();
+    for (address, code, cells) in lines {
+        let line = match cells {
+            Some(cells) => format!(
+                "{:<address_width$}  {:<code_width$}   -> {}",
+                address, code, cells
+            ),
+            None => format!("{:<address_width$}  {}", address, code),
+        };
+        listing += line.trim_end();
+        listing.push('\n');
+    }
+
+    let mut labels: Vec<(&str, usize)> = program.labels.into_iter().collect();
+    labels.sort_by_key(|&(label, address)| (address, label));
+    if !labels.is_empty() {
+        listing += "\nLabels:\n";
+        for (label, address) in labels {
+            listing += &format!("{:<label_width$}  {:0address_width$}\n", label, address);
+        }
+    }
+    let mut equates: Vec<(&str, i16)> = program.equates.into_iter().collect();
+    equates.sort();
+    if !equates.is_empty() {
+        listing += "\nConstants:\n";
+        for (name, value) in equates {
+            listing += &format!("{:<label_width$}  {}\n", name, value);
+        }
+    }
+    Ok(listing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Adds two numbers from the input
+    const ADD_PROGRAM: &str = "INP\nSTA first\nINP\nADD first\nOUT\nHLT\nfirst DAT";
+
+    #[test]
+    fn reports_every_bad_line() {
+        let error = assemble("LDX 5\nOUT\nADD 150\nFOO 3\nDAT 1000").unwrap_err();
+        assert_eq!(
+            error,
+            AssemblerError::ParseErrors(vec![
+                AssemblerError::InvalidOpcode {
+                    line: 1,
+                    opcode: "LDX".to_string()
+                },
+                AssemblerError::InvalidOpcode {
+                    line: 4,
+                    opcode: "FOO".to_string()
+                },
+            ])
+        );
+        let error = assemble("ADD 150\nOUT\nDAT 1000").unwrap_err();
+        let AssemblerError::ParseErrors(errors) = error else {
+            panic!("expected two errors, got {:?}", error);
+        };
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].line(), Some(1));
+        assert_eq!(errors[1].line(), Some(3));
+    }
+
+    #[test]
+    fn single_error_is_returned_on_its_own() {
+        assert_eq!(
+            assemble("INP\nLDX 5"),
+            Err(AssemblerError::InvalidOpcode {
+                line: 2,
+                opcode: "LDX".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn duplicate_labels_are_an_error() {
+        let source = "loop INP\nOUT\nloop BRZ loop\nHLT";
+        assert_eq!(
+            assemble(source),
+            Err(AssemblerError::DuplicateLabel {
+                line: 3,
+                label: "loop".to_string(),
+                first_line: 1
+            })
+        );
+        let source = "loop INP\nBRA loop\nloop EQU 5";
+        assert_eq!(
+            assemble(source),
+            Err(AssemblerError::DuplicateLabel {
+                line: 3,
+                label: "loop".to_string(),
+                first_line: 1
+            })
+        );
+    }
+
+    #[test]
+    fn undefined_label_names_the_line() {
+        let error = assemble("INP\nOUT\nBRZ done\nHLT").unwrap_err();
+        assert_eq!(
+            error,
+            AssemblerError::UndefinedLabel {
+                line: 3,
+                label: "done".to_string()
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Undefined label 'done' referenced on line 3"
+        );
+    }
+
+    #[test]
+    fn inline_comments_are_ignored() {
+        assert_eq!(assemble("INP // read"), Ok(vec![901]));
+        assert_eq!(assemble("loop BRA loop // jump"), Ok(vec![600]));
+        assert_eq!(
+            assemble("// A whole line\nOUT ; print\nHLT # stop"),
+            Ok(vec![902, 0])
+        );
+    }
+
+    #[test]
+    fn opcodes_are_case_insensitive() {
+        let expected = vec![901, 306, 901, 106, 902, 0, 0];
+        assert_eq!(assemble(ADD_PROGRAM), Ok(expected.clone()));
+        assert_eq!(assemble(&ADD_PROGRAM.to_lowercase()), Ok(expected.clone()));
+        let mixed = "Inp\nsta first\niNP\nAdd first\nout\nhlt\nfirst dat";
+        assert_eq!(assemble(mixed), Ok(expected));
+    }
+
+    #[test]
+    fn labels_are_case_sensitive() {
+        assert_eq!(assemble("Loop BRA loop\nloop HLT"), Ok(vec![601, 0]));
+    }
+
+    #[test]
+    fn character_literals_are_ascii_codes() {
+        assert_eq!(assemble("DAT 'h'"), Ok(vec![104]));
+        assert_eq!(assemble("DAT ' ' '#' ';'"), Ok(vec![32, 35, 59]));
+        assert_eq!(
+            assemble("DAT '\\n' '\\t' '\\0' '\\\\' '\\''"),
+            Ok(vec![10, 9, 0, 92, 39])
+        );
+        assert_eq!(assemble("LDA 'A' // load"), Ok(vec![565]));
+    }
+
+    #[test]
+    fn bad_character_literals_are_errors() {
+        for literal in ["'ab'", "''", "'\\q'", "'a", "'é'"] {
+            assert_eq!(
+                assemble(&format!("DAT {}", literal)),
+                Err(AssemblerError::InvalidOperand {
+                    line: 1,
+                    operand: literal.to_string()
+                }),
+                "assembling {}",
+                literal
+            );
+        }
+    }
+
+    #[test]
+    fn hex_and_binary_operands() {
+        assert_eq!(assemble("DAT 0x10"), Ok(vec![16]));
+        assert_eq!(assemble("ADD 0b11"), Ok(vec![103]));
+        assert_eq!(assemble("DAT -0x1F 0XFF +0B101"), Ok(vec![-31, 255, 5]));
+        assert_eq!(
+            assemble("ADD 0x64"),
+            Err(AssemblerError::InvalidOperand {
+                line: 1,
+                operand: "100".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn bad_hex_and_binary_operands_are_errors() {
+        for operand in ["0x", "0b2", "0xG", "0x-1", "0x10000"] {
+            assert_eq!(
+                assemble(&format!("DAT {}", operand)),
+                Err(AssemblerError::InvalidOperand {
+                    line: 1,
+                    operand: operand.to_string()
+                }),
+                "assembling {}",
+                operand
+            );
+        }
+    }
+
+    #[test]
+    fn listing_of_add_program() {
+        assert_eq!(
+            listing(ADD_PROGRAM).unwrap(),
+            "00         INP      -> 901
+01         STA 06   -> 306
+02         INP      -> 901
+03         ADD 06   -> 106
+04         OUT      -> 902
+05         HLT      -> 0
+06  first  DAT      -> 0
+
+Labels:
+first  06
+"
+        );
+    }
+
+    #[test]
+    fn listing_shows_directives_and_constants() {
+        let source = "TEN EQU 10\nORG 5\nloop LDA table\nBRA loop\ntable DAT TEN 'a'";
+        assert_eq!(
+            listing(source).unwrap(),
+            "    TEN    EQU 10
+           ORG 05
+05  loop   LDA 07      -> 507
+06         BRA 05      -> 605
+07  table  DAT 10 97   -> 10 97
+
+Labels:
+loop   05
+table  07
+
+Constants:
+TEN    10
+"
+        );
+    }
+
+    #[test]
+    fn assembles_for_bigger_architectures() {
+        let architecture = Architecture::new(3).unwrap();
+        let source = "BRA target\nORG 500\ntarget LDA value\nHLT\nvalue DAT 9999";
+        let machine_code = assemble_for(source, architecture).unwrap();
+        assert_eq!(machine_code.len(), 503);
+        assert_eq!(machine_code[0], 6500);
+        assert_eq!(machine_code[500..], [5502, 0, 9999]);
+        assert_eq!(
+            assemble_for("INP\nOUT\nOTC", architecture),
+            Ok(vec![9001, 9002, 9022])
+        );
+        // The classic architecture doesn't have address 500, or room for 9999
+        assert!(assemble(source).is_err());
+        assert!(listing_for(source, architecture)
+            .unwrap()
+            .contains("500  target  LDA 502    -> 5502"));
+    }
+
+    #[test]
+    fn programs_must_fit_in_ram() {
+        let architecture = Architecture::new(1).unwrap();
+        assert_eq!(
+            assemble_for("DAT 1 2 3 4 5 6 7 8 9 10 11", architecture),
+            Err(AssemblerError::ProgramTooLong { excess_cells: 1 })
+        );
+        assert_eq!(assemble_for("INP\nOUT", architecture), Ok(vec![91, 92]));
+        assert_eq!(
+            assemble_for("OTC", architecture),
+            Err(AssemblerError::InvalidOpcode {
+                line: 1,
+                opcode: "OTC".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn assembles_add_program() {
+        assert_eq!(
+            assemble(ADD_PROGRAM),
+            Ok(vec![901, 306, 901, 106, 902, 0, 0])
+        );
+        assert_eq!(assemble("STO 5\nCOB\nINC\nOTC"), Ok(vec![305, 0, 921, 922]));
+        assert_eq!(
+            assemble("MUL 5\nDIV 42\nAST 99"),
+            Ok(vec![-105, -242, -399])
+        );
+    }
+
+    #[test]
+    fn labels_can_be_used_before_they_are_defined() {
+        let source = "LDA pointer\nHLT\npointer DAT target\ntarget DAT 5";
+        assert_eq!(assemble(source), Ok(vec![502, 0, 3, 5]));
+    }
+
+    #[test]
+    fn origin_leaves_a_gap() {
+        let source = "BRA start\nORG 10\nstart OUT\nHLT";
+        assert_eq!(
+            assemble(source),
+            Ok(vec![610, 0, 0, 0, 0, 0, 0, 0, 0, 0, 902, 0])
+        );
+        // ORG can't go backwards, or past the end of RAM
+        assert_eq!(
+            assemble("OUT\nOUT\nORG 1"),
+            Err(AssemblerError::InvalidOperand {
+                line: 3,
+                operand: "1".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("ORG 100"),
+            Err(AssemblerError::InvalidOperand {
+                line: 1,
+                operand: "100".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn dat_can_have_several_values() {
+        assert_eq!(
+            assemble("LDA table\nHLT\ntable DAT 1 -2 3\nend DAT\nBRA end"),
+            Ok(vec![502, 0, 1, -2, 3, 0, 605])
+        );
+    }
+
+    #[test]
+    fn labels_after_dat_blocks() {
+        let source = "LDA after\nHLT\nDAT 1 2 3\nafter DAT 9";
+        assert_eq!(assemble(source), Ok(vec![505, 0, 1, 2, 3, 9]));
+    }
+
+    #[test]
+    fn each_comment_style_gives_the_same_code() {
+        let expected = assemble("INP\nSTA 99\nINP\nADD 99\nOUT\nHLT");
+        for marker in ["//", ";", "#"] {
+            let source = format!(
+                "{marker} Adds two numbers\nINP {marker} first\nSTA 99\nINP\nADD 99 {marker} second\nOUT\nHLT"
+            );
+            assert_eq!(assemble(&source), expected, "comment marker {marker}");
+        }
+    }
+
+    #[test]
+    fn equ_defines_constants() {
+        let source = "TEN EQU 10\nSCREEN EQU 0x5A\nLDA TEN\nSTA SCREEN\nDAT TEN";
+        assert_eq!(assemble(source), Ok(vec![510, 390, 10]));
+        assert_eq!(
+            assemble("EQU 5"),
+            Err(AssemblerError::MissingLabel { line: 1 })
+        );
+        assert_eq!(
+            assemble("FIVE EQU"),
+            Err(AssemblerError::MissingOperand { line: 1 })
+        );
+        // Errors name the constant rather than its value
+        assert_eq!(
+            assemble("BIG EQU 5000\nLDA BIG"),
+            Err(AssemblerError::InvalidOperand {
+                line: 2,
+                operand: "BIG".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("START EQU 5\nORG START\nOUT"),
+            Ok(vec![0, 0, 0, 0, 0, 902])
+        );
+    }
+
+    #[test]
+    fn operand_on_fixed_instruction_is_a_warning() {
+        assert_eq!(
+            assemble_with_warnings("INP 99\nout 5\nHLT"),
+            Ok((
+                vec![901, 902, 0],
+                vec![
+                    AssemblerWarning::IgnoredOperand {
+                        line: 1,
+                        opcode: "INP".to_string()
+                    },
+                    AssemblerWarning::IgnoredOperand {
+                        line: 2,
+                        opcode: "out".to_string()
+                    },
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn programs_longer_than_ram_are_an_error() {
+        let source = "OUT\n".repeat(101);
+        assert_eq!(
+            assemble(&source),
+            Err(AssemblerError::ProgramTooLong { excess_cells: 1 })
+        );
+        assert_eq!(
+            assemble(&"DAT 1\n".repeat(100)).map(|cells| cells.len()),
+            Ok(100)
+        );
+    }
+
+    #[test]
+    fn opcodes_cant_be_labels() {
+        assert_eq!(
+            assemble("out DAT 5"),
+            Err(AssemblerError::ReservedLabel {
+                line: 1,
+                label: "out".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn address_instructions_need_an_operand() {
+        assert_eq!(
+            assemble("INP\nADD"),
+            Err(AssemblerError::MissingOperand { line: 2 })
+        );
+        assert_eq!(
+            assemble("loop"),
+            Err(AssemblerError::MissingOpcode { line: 1 })
+        );
+        assert_eq!(
+            assemble("ADD 1 2"),
+            Err(AssemblerError::UnexpectedToken {
+                line: 1,
+                token: "2".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn render_underlines_the_problem() {
+        let source = "INP\n\tLDX 12";
+        let error = assemble(source).unwrap_err();
+        assert_eq!(
+            error.render(source),
+            "Line 2: 'LDX' is not a valid opcode\n  |\n2 | \tLDX 12\n  | \t^^^"
+        );
+        let error = AssemblerError::ProgramTooLong { excess_cells: 2 };
+        assert_eq!(error.render(source), error.to_string());
+    }
+}
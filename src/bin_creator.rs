@@ -1,9 +1,5 @@
-use std::{
-    env,
-    error::Error,
-    fs,
-    io::{self, BufRead},
-};
+use rusty_man_computer::{create_bin, read_pasted_text, Architecture};
+use std::{env, error::Error, fs, io, path::Path, process};
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = env::args().collect();
@@ -11,28 +7,18 @@ fn main() -> Result<(), Box<dyn Error>> {
         println!("Please provide a filename to write the binary data to");
         return Ok(());
     }
-    let filename = &args[1];
+    let filename = Path::new(&args[1]);
 
-    // Let the user paste in a string
-    println!("Paste in the memory data:");
-    let mut line = String::new();
-    let stdin = io::stdin();
-    stdin.lock().read_line(&mut line)?;
-
-    // Split the string into a vector i16 signed ints
-    let memory_data_items: Vec<i16> = line
-        .split_whitespace()
-        .map(|s| s.parse::<i16>().unwrap())
-        .collect();
-
-    // memory_data_items[0].to_be_bytes()
-    let memory_data_bytes: Vec<u8> = memory_data_items
-        .iter()
-        .flat_map(|&i| i.to_be_bytes().to_vec())
-        .collect();
-
-    // Write the memory data to a binary file
-    fs::write(filename, memory_data_bytes)?;
+    println!("Paste in the memory data, then press Enter on a blank line:");
+    let text = read_pasted_text(io::stdin().lock())?;
+    // Values outside of -999 to 999 can't fit in a memory cell, so they are rejected
+    match create_bin(&text, Architecture::CLASSIC) {
+        Ok(bytes) => fs::write(filename, bytes)?,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            process::exit(1);
+        }
+    }
 
     Ok(())
 }
@@ -0,0 +1,129 @@
+use crate::{
+    instruction::{decode_for, Instruction},
+    Architecture,
+};
+
+/// Decodes a cell that is likely to be an instruction rather than data
+fn decode_instruction(cell: i16, architecture: Architecture) -> Option<Instruction> {
+    match decode_for(cell, architecture) {
+        // Cells like 001 to 099 would halt the computer, but are much more likely to be data
+        Ok(Instruction::Halt) if cell != 0 => None,
+        // There's no mnemonic for the unused 9xx cells, so they can only be written with DAT
+        Ok(Instruction::NoOperation) => None,
+        Ok(instruction) => Some(instruction),
+        Err(_) => None,
+    }
+}
+
+/// Gets the mnemonic of the instruction stored in a memory cell, or `None` if it isn't a valid instruction
+pub fn mnemonic(cell: i16) -> Option<&'static str> {
+    decode_instruction(cell, Architecture::CLASSIC).map(Instruction::mnemonic)
+}
+
+/// Converts a single memory cell back into a line of assembly, e.g. `599` becomes `LDA 99`
+pub fn disassemble_cell(cell: i16) -> String {
+    disassemble_cell_for(cell, Architecture::CLASSIC)
+}
+
+/// Converts a single memory cell from a computer with a different memory size back into a line
+/// of assembly, e.g. `5500` becomes `LDA 500` with 1000 cells
+pub fn disassemble_cell_for(cell: i16, architecture: Architecture) -> String {
+    let Some(instruction) = decode_instruction(cell, architecture) else {
+        // Anything else can't be a valid instruction, so it must be data
+        return format!("DAT {}", cell);
+    };
+    match instruction.address() {
+        Some(address) => format!(
+            "{} {:0width$}",
+            instruction.mnemonic(),
+            address,
+            width = architecture.address_digits() as usize
+        ),
+        None => instruction.mnemonic().to_string(),
+    }
+}
+
+/// Converts memory cells back into assembly, with one line for each cell.
+///
+/// Trailing empty cells are left out, because they are just the default contents of RAM.
+pub fn disassemble(cells: &[i16]) -> String {
+    let used_length = cells
+        .iter()
+        .rposition(|&cell| cell != 0)
+        .map_or(0, |index| index + 1);
+    cells[..used_length]
+        .iter()
+        .map(|&cell| disassemble_cell(cell) + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::{assemble, assemble_for};
+
+    #[test]
+    fn every_cell_assembles_back_to_itself() {
+        for cell in -999..=999 {
+            let assembly = disassemble_cell(cell);
+            assert_eq!(
+                assemble(&assembly),
+                Ok(vec![cell]),
+                "assembling {}",
+                assembly
+            );
+        }
+    }
+
+    #[test]
+    fn every_cell_assembles_back_to_itself_with_more_digits() {
+        let architecture = Architecture::new(3).unwrap();
+        for cell in -9999..=9999 {
+            let assembly = disassemble_cell_for(cell, architecture);
+            assert_eq!(
+                assemble_for(&assembly, architecture),
+                Ok(vec![cell]),
+                "assembling {}",
+                assembly
+            );
+        }
+    }
+
+    #[test]
+    fn disassembles_programs() {
+        let cells = [901, 308, 901, 108, 902, -308, 0, 42, 0, 0];
+        let assembly = disassemble(&cells);
+        assert_eq!(
+            assembly,
+            "INP\nSTA 08\nINP\nADD 08\nOUT\nAST 08\nHLT\nDAT 42\n"
+        );
+        // The trailing empty cells are left out
+        assert_eq!(assemble(&assembly), Ok(cells[..8].to_vec()));
+    }
+
+    #[test]
+    fn disassembles_cells() {
+        assert_eq!(disassemble_cell(599), "LDA 99");
+        assert_eq!(disassemble_cell(105), "ADD 05");
+        assert_eq!(disassemble_cell(-142), "MUL 42");
+        assert_eq!(disassemble_cell(0), "HLT");
+        // These are much more likely to be data than instructions
+        assert_eq!(disassemble_cell(42), "DAT 42");
+        assert_eq!(disassemble_cell(450), "DAT 450");
+        assert_eq!(disassemble_cell(950), "DAT 950");
+        assert_eq!(disassemble_cell(-42), "DAT -42");
+        let architecture = Architecture::new(3).unwrap();
+        assert_eq!(disassemble_cell_for(5500, architecture), "LDA 500");
+        assert_eq!(disassemble_cell_for(9001, architecture), "INP");
+    }
+
+    #[test]
+    fn mnemonics_of_cells() {
+        assert_eq!(mnemonic(901), Some("INP"));
+        assert_eq!(mnemonic(922), Some("OTC"));
+        assert_eq!(mnemonic(-305), Some("AST"));
+        assert_eq!(mnemonic(0), Some("HLT"));
+        assert_eq!(mnemonic(5), None);
+        assert_eq!(mnemonic(400), None);
+    }
+}
@@ -0,0 +1,16 @@
+use rusty_man_computer::{bytes_to_cells, disassembler::disassemble};
+use std::{env, error::Error, fs};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        println!("Please provide the filename of a memory dump (.bin file) to disassemble");
+        return Ok(());
+    }
+    let filename = &args[1];
+
+    let data = fs::read(filename)?;
+    print!("{}", disassemble(&bytes_to_cells(&data)));
+
+    Ok(())
+}
@@ -0,0 +1,204 @@
+use std::{error::Error, fmt};
+
+use crate::Architecture;
+
+/// An instruction decoded from a memory cell. Instructions that use memory hold the address
+/// from the last digits of the cell, e.g. the last two digits with 100 cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// `HLT` - Any cell from 000 to 099 (with the classic architecture)
+    Halt,
+    Add(usize),
+    Subtract(usize),
+    Store(usize),
+    Load(usize),
+    Branch(usize),
+    BranchIfZero(usize),
+    BranchIfPositive(usize),
+    /// `INP` - 901
+    Input,
+    /// `OUT` - 902
+    Output,
+    /// `INC` - 921 (Non-standard instruction)
+    InputCharacter,
+    /// `OTC` - 922 (Non-standard instruction)
+    OutputCharacter,
+    /// Any other cell starting with a 9, e.g. 950, which does nothing
+    NoOperation,
+    /// `MUL` - Stored as a negative number, e.g. -142 is `MUL 42` (Non-standard instruction)
+    Multiply(usize),
+    /// `DIV` - Stored as a negative number, e.g. -242 is `DIV 42` (Non-standard instruction)
+    Divide(usize),
+    /// `AST` - Stored as a negative number, e.g. -342 is `AST 42`. Stops with an error unless the
+    /// accumulator holds the same value as the memory address (Non-standard instruction)
+    Assert(usize),
+}
+
+impl Instruction {
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Instruction::Halt => "HLT",
+            Instruction::Add(_) => "ADD",
+            Instruction::Subtract(_) => "SUB",
+            Instruction::Store(_) => "STA",
+            Instruction::Load(_) => "LDA",
+            Instruction::Branch(_) => "BRA",
+            Instruction::BranchIfZero(_) => "BRZ",
+            Instruction::BranchIfPositive(_) => "BRP",
+            Instruction::Input => "INP",
+            Instruction::Output => "OUT",
+            Instruction::InputCharacter => "INC",
+            Instruction::OutputCharacter => "OTC",
+            Instruction::NoOperation => "NOP",
+            Instruction::Multiply(_) => "MUL",
+            Instruction::Divide(_) => "DIV",
+            Instruction::Assert(_) => "AST",
+        }
+    }
+
+    /// The memory address the instruction uses, if it has one
+    pub fn address(self) -> Option<usize> {
+        match self {
+            Instruction::Add(address)
+            | Instruction::Subtract(address)
+            | Instruction::Store(address)
+            | Instruction::Load(address)
+            | Instruction::Branch(address)
+            | Instruction::BranchIfZero(address)
+            | Instruction::BranchIfPositive(address)
+            | Instruction::Multiply(address)
+            | Instruction::Divide(address)
+            | Instruction::Assert(address) => Some(address),
+            Instruction::Halt
+            | Instruction::Input
+            | Instruction::Output
+            | Instruction::InputCharacter
+            | Instruction::OutputCharacter
+            | Instruction::NoOperation => None,
+        }
+    }
+}
+
+/// A memory cell that doesn't hold a valid instruction, e.g. anything starting with a 4
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeError {
+    pub cell: i16,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} is not a valid instruction", self.cell)
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Combines an opcode and a memory address into a cell, e.g. opcode 5 and address 42 make 542.
+/// The non-standard instructions have negative opcodes, e.g. -1 and 42 make -142 (`MUL 42`).
+/// Returns `None` if the address is too big to fit in the last two digits.
+pub fn encode(opcode: i16, address: usize) -> Option<i16> {
+    encode_for(opcode, address, Architecture::CLASSIC)
+}
+
+/// Like `encode`, but for a computer with a different memory size, e.g. opcode 5 and address 500
+/// make 5500 with 1000 cells
+pub fn encode_for(opcode: i16, address: usize, architecture: Architecture) -> Option<i16> {
+    let memory_size = architecture.memory_size();
+    if address >= memory_size {
+        return None;
+    }
+    let address = address as i16;
+    let base = opcode * memory_size as i16;
+    Some(if opcode < 0 {
+        base - address
+    } else {
+        base + address
+    })
+}
+
+pub fn decode(cell: i16) -> Result<Instruction, DecodeError> {
+    decode_for(cell, Architecture::CLASSIC)
+}
+
+/// Decodes a cell from a computer with a different memory size, where the address is in the last
+/// `address_digits` digits, e.g. 5500 is `LDA 500` with 1000 cells
+pub fn decode_for(cell: i16, architecture: Architecture) -> Result<Instruction, DecodeError> {
+    let memory_size = architecture.memory_size() as i16;
+    let opcode = cell / memory_size;
+    let address = (cell % memory_size).unsigned_abs() as usize;
+    match (opcode, address) {
+        (0, _) => Ok(Instruction::Halt),
+        (1, _) => Ok(Instruction::Add(address)),
+        (2, _) => Ok(Instruction::Subtract(address)),
+        (3, _) => Ok(Instruction::Store(address)),
+        (5, _) => Ok(Instruction::Load(address)),
+        (6, _) => Ok(Instruction::Branch(address)),
+        (7, _) => Ok(Instruction::BranchIfZero(address)),
+        (8, _) => Ok(Instruction::BranchIfPositive(address)),
+        (9, 1) => Ok(Instruction::Input),
+        (9, 2) => Ok(Instruction::Output),
+        (9, 21) => Ok(Instruction::InputCharacter),
+        (9, 22) => Ok(Instruction::OutputCharacter),
+        (9, _) => Ok(Instruction::NoOperation),
+        (-1, _) => Ok(Instruction::Multiply(address)),
+        (-2, _) => Ok(Instruction::Divide(address)),
+        (-3, _) => Ok(Instruction::Assert(address)),
+        _ => Err(DecodeError { cell }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_each_opcode() {
+        let cases = [
+            (0, Instruction::Halt),
+            (42, Instruction::Halt),
+            (142, Instruction::Add(42)),
+            (242, Instruction::Subtract(42)),
+            (342, Instruction::Store(42)),
+            (542, Instruction::Load(42)),
+            (642, Instruction::Branch(42)),
+            (742, Instruction::BranchIfZero(42)),
+            (842, Instruction::BranchIfPositive(42)),
+            (901, Instruction::Input),
+            (902, Instruction::Output),
+            (921, Instruction::InputCharacter),
+            (922, Instruction::OutputCharacter),
+            (900, Instruction::NoOperation),
+            (950, Instruction::NoOperation),
+            (-142, Instruction::Multiply(42)),
+            (-242, Instruction::Divide(42)),
+            (-342, Instruction::Assert(42)),
+        ];
+        for (cell, instruction) in cases {
+            assert_eq!(decode(cell), Ok(instruction), "decoding {}", cell);
+        }
+    }
+
+    #[test]
+    fn opcode_4_is_a_decode_error() {
+        assert_eq!(decode(400), Err(DecodeError { cell: 400 }));
+        assert_eq!(decode(499), Err(DecodeError { cell: 499 }));
+        assert_eq!(decode(-442), Err(DecodeError { cell: -442 }));
+    }
+
+    #[test]
+    fn decodes_with_more_address_digits() {
+        let architecture = Architecture::new(3).unwrap();
+        assert_eq!(decode_for(6500, architecture), Ok(Instruction::Branch(500)));
+        assert_eq!(decode_for(9002, architecture), Ok(Instruction::Output));
+        assert_eq!(decode_for(902, architecture), Ok(Instruction::Halt));
+    }
+
+    #[test]
+    fn encode_is_the_opposite_of_decode() {
+        assert_eq!(encode(5, 42), Some(542));
+        assert_eq!(encode(-1, 42), Some(-142));
+        assert_eq!(encode(5, 100), None);
+        let architecture = Architecture::new(3).unwrap();
+        assert_eq!(encode_for(6, 500, architecture), Some(6500));
+    }
+}
@@ -0,0 +1,2809 @@
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    error::Error,
+    fmt, fs,
+    io::{self, BufRead, IsTerminal, Write},
+    mem,
+    ops::RangeInclusive,
+    path::Path,
+};
+
+pub mod assembler;
+pub mod disassembler;
+pub mod instruction;
+pub mod monitor;
+pub mod output;
+pub mod validator;
+
+use assembler::AssemblerError;
+use instruction::Instruction;
+use output::{Output, OutputEvent};
+
+#[derive(Default)]
+struct Registers {
+    program_counter: usize,
+    instruction_register: i16,
+    address_register: usize,
+    accumulator: i16,
+    /// Set when the last value put into the Accumulator was below zero, before being wrapped
+    /// round to fit in a cell
+    negative_flag: bool,
+}
+
+/// A copy of the register values at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisterSnapshot {
+    pub program_counter: usize,
+    pub instruction_register: i16,
+    pub address_register: usize,
+    pub accumulator: i16,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub negative_flag: bool,
+}
+
+/// The contents of RAM, the registers and the output so far, which can be put back with
+/// `Computer::restore`. Keep a list of these to be able to step backwards through a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MachineState {
+    pub ram: Vec<i16>,
+    pub registers: RegisterSnapshot,
+    pub output: String,
+}
+
+/// The names of the columns written to `ComputerConfig::trace_writer`
+pub const TRACE_CSV_HEADER: &str = "cycle,program_counter,mnemonic,operand,accumulator,output";
+
+/// A record of one executed instruction, kept when `ComputerConfig::trace` is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TraceEntry {
+    /// Which clock cycle this was, starting from 1
+    pub cycle: u64,
+    /// The address that the instruction was fetched from
+    pub address: usize,
+    pub opcode: i16,
+    pub operand: usize,
+    /// The value of the accumulator after the instruction was executed
+    pub accumulator: i16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MemoryAccess {
+    /// The cell was used as an operand, e.g. by `LDA` or `ADD`
+    Read,
+    /// The cell was written to by `STA`
+    Write,
+}
+
+/// A record of an instruction accessing a watched memory address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WatchEvent {
+    pub cycle: u64,
+    /// The watched address that was accessed
+    pub address: usize,
+    /// The instruction that accessed it, e.g. `399`
+    pub instruction: i16,
+    pub access: MemoryAccess,
+    pub old_value: i16,
+    /// The value of the cell afterwards, which is the same as `old_value` for reads
+    pub new_value: i16,
+}
+
+/// A record of `STA` overwriting a cell that has already been run as an instruction, kept when
+/// `ComputerConfig::warn_self_modifying` is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelfModifyingWrite {
+    pub cycle: u64,
+    /// The address of the `STA` instruction
+    pub address: usize,
+    /// The address of the instruction that was overwritten
+    pub overwritten_address: usize,
+}
+
+/// One line of the JSON event stream, written after each clock cycle when
+/// `ComputerConfig::event_writer` is set
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CycleEvent {
+    pub cycle: u64,
+    /// The address that the instruction was fetched from
+    pub program_counter: usize,
+    /// e.g. `LDA`
+    pub mnemonic: String,
+    /// The memory address the instruction used, if it has one
+    pub operand: Option<usize>,
+    /// The value of the accumulator after the instruction was executed
+    pub accumulator: i16,
+    /// Anything the instruction output, or an empty string
+    pub output: String,
+}
+
+/// What happened during a single call to `Computer::step`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepResult {
+    pub opcode: i16,
+    pub operand: usize,
+    pub program_counter_before: usize,
+    pub program_counter_after: usize,
+    pub accumulator: i16,
+    /// True if the instruction stopped the computer (e.g. `HLT`)
+    pub halted: bool,
+}
+
+/// Whether the computer carried on after executing an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CycleOutcome {
+    Continued,
+    /// The instruction was `HLT`
+    Halted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    Halted,
+    /// The program counter reached a breakpoint at the given address, which hasn't been executed yet
+    BreakpointHit(usize),
+    CycleLimitReached,
+    /// The condition given to `Computer::run_until` became true
+    ConditionMet,
+}
+
+/// Something that went wrong while executing a program, which stops the computer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunError {
+    /// The cell at `address` doesn't hold a valid instruction (e.g. anything starting with a 4)
+    IllegalInstruction { instruction: i16, address: usize },
+    /// The `DIV` instruction at `address` tried to divide by zero
+    DivisionByZero { address: usize },
+    /// The program ran past the last memory address without halting
+    ProgramCounterOverflow,
+    /// An input instruction was executed, but there was no more input to read
+    InputExhausted,
+    /// A queued input value was too big, or too small, to fit in the accumulator
+    InvalidInput { value: i16 },
+    /// An arithmetic instruction at `address` went outside the range a cell can hold, and
+    /// `trap_on_overflow` is enabled
+    ArithmeticOverflow { address: usize },
+    /// Writing program output to the output's writer failed
+    OutputFailed(io::ErrorKind),
+    /// The program output more than `max_output_bytes` bytes
+    OutputLimitExceeded { limit: usize },
+    /// The instruction at `address` uses a memory address that is outside of RAM, which can only
+    /// happen if it was passed straight to `Computer::execute`
+    OperandOutOfRange { address: usize, operand: usize },
+    /// The `AST` instruction at `address` found that the accumulator didn't hold `expected`
+    AssertionFailed {
+        address: usize,
+        expected: i16,
+        actual: i16,
+    },
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::IllegalInstruction {
+                instruction,
+                address,
+            } => {
+                write!(
+                    f,
+                    "Illegal instruction {} at address {:02}",
+                    instruction, address
+                )
+            }
+            RunError::DivisionByZero { address } => {
+                write!(f, "Division by zero at address {:02}", address)
+            }
+            RunError::ProgramCounterOverflow => {
+                write!(f, "Program counter went past the last memory address")
+            }
+            RunError::InputExhausted => write!(f, "No more input available"),
+            RunError::InvalidInput { value } => {
+                write!(f, "Input value {} is out of range", value)
+            }
+            RunError::ArithmeticOverflow { address } => {
+                write!(f, "Arithmetic overflow at address {:02}", address)
+            }
+            RunError::OutputFailed(kind) => write!(f, "Failed to write output: {}", kind),
+            RunError::OutputLimitExceeded { limit } => {
+                write!(f, "Output limit of {} bytes exceeded", limit)
+            }
+            RunError::OperandOutOfRange { address, operand } => {
+                write!(
+                    f,
+                    "The instruction at address {:02} uses address {}, which is outside of RAM",
+                    address, operand
+                )
+            }
+            RunError::AssertionFailed {
+                address,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "Assertion failed at address {:02}: expected {}, but the accumulator is {}",
+                    address, expected, actual
+                )
+            }
+        }
+    }
+}
+
+impl Error for RunError {}
+
+/// Details of how much data was loaded into RAM, so that malformed memory dumps can be reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadReport {
+    pub cells_loaded: usize,
+    /// How many cells there were after the end of RAM, which were ignored
+    pub cells_truncated: usize,
+    /// True if the data had an odd number of bytes, so the last cell only had its high byte
+    pub odd_byte_count: bool,
+}
+
+/// A token in a text memory file that isn't a valid memory cell value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCellError {
+    pub token: String,
+    /// The largest value a cell can hold, e.g. 999 for the classic architecture
+    pub max_value: i16,
+}
+
+impl fmt::Display for ParseCellError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "'{}' is not a whole number from -{} to {}",
+            self.token, self.max_value, self.max_value
+        )
+    }
+}
+
+impl Error for ParseCellError {}
+
+/// Counts of what the computer has executed so far
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionStats {
+    pub total_cycles: u64,
+    /// How many times each instruction was executed, keyed by its mnemonic
+    pub instruction_counts: BTreeMap<&'static str, u64>,
+}
+
+/// Whether to use ANSI escape codes to colour and bold what the computer prints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// Only use colours if stdout is a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn is_enabled(self) -> bool {
+        match self {
+            ColorMode::Auto => io::stdout().is_terminal(),
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+/// The size of the computer's memory, which also sets how many digits each cell has. The classic
+/// Little Man Computer has 100 cells, so addresses have 2 digits and values go up to 999.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Architecture {
+    address_digits: u32,
+}
+
+impl Architecture {
+    pub const CLASSIC: Architecture = Architecture { address_digits: 2 };
+
+    /// Returns `None` unless `address_digits` is from 1 to 3, because cells are stored as `i16`
+    pub fn new(address_digits: u32) -> Option<Self> {
+        (1..=3)
+            .contains(&address_digits)
+            .then_some(Architecture { address_digits })
+    }
+
+    pub fn address_digits(self) -> u32 {
+        self.address_digits
+    }
+
+    /// How many cells there are in RAM, e.g. 100 for the classic architecture
+    pub fn memory_size(self) -> usize {
+        10_usize.pow(self.address_digits)
+    }
+
+    /// The largest value a cell can hold. Instructions have one more digit than addresses, for
+    /// the opcode, e.g. 999 for the classic architecture.
+    pub fn max_value(self) -> i16 {
+        10_i16.pow(self.address_digits + 1) - 1
+    }
+
+    /// The values a cell can hold, from `-max_value()` to `max_value()`
+    pub fn value_range(self) -> RangeInclusive<i16> {
+        -self.max_value()..=self.max_value()
+    }
+}
+
+impl Default for Architecture {
+    fn default() -> Self {
+        Architecture::CLASSIC
+    }
+}
+
+/// How RAM is shown when printing the computer's state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RamView {
+    /// A grid of the numbers in each cell
+    #[default]
+    Numeric,
+    /// A list of each cell's number and what it means as assembly, e.g. `599 LDA 99`
+    Disassembled,
+}
+
+/// A standard stream that the program's output can be written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// How `OTC` turns the accumulator into a character
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OtcEncoding {
+    /// Values from 0 to 127 are ASCII codes
+    #[default]
+    Ascii,
+    /// Any value that isn't negative is a Unicode code point, e.g. 233 is `é`
+    Utf8,
+}
+
+/// Where INP and INC get their values from
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum InputSource {
+    /// Ask for each value on stdin
+    #[default]
+    Interactive,
+    /// Read these values in order, and stop with an error once they run out, or when one doesn't
+    /// fit in the accumulator
+    Vector(Vec<i16>),
+    /// Make up values from this seed, which are the same every time the program is run with it.
+    /// INP gets any value a cell can hold, and INC gets a printable ASCII character.
+    Seeded(u64),
+}
+
+/// What `OTC` does with a value that isn't a character code in its `OtcEncoding`, e.g. a negative
+/// value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvalidCharacter {
+    /// Output the character for the value's lowest byte, e.g. 321 is `A`
+    #[default]
+    LowestByte,
+    /// Don't output anything
+    Skip,
+    /// Output `�` (U+FFFD) instead
+    Replace,
+}
+
+pub struct ComputerConfig {
+    /// Stop running after this many clock cycles, even if the program hasn't halted
+    pub max_cycles: Option<u64>,
+    /// How many characters of output to show in each row when printing the computer's state
+    pub output_line_width: usize,
+    /// Stop with an error when ADD, SUB, MUL or DIV overflow, instead of wrapping round
+    pub trap_on_overflow: bool,
+    /// Record every executed instruction, so it can be read back with `Computer::trace`
+    pub trace: bool,
+    pub input_source: InputSource,
+    /// Don't print anything except the program's own output, e.g. when piping it somewhere else.
+    /// Program output is only printed if the computer was created with an `Output` writer.
+    pub quiet: bool,
+    pub color: ColorMode,
+    pub ram_view: RamView,
+    /// How many cells to show in each row of the numeric RAM view
+    pub ram_columns: usize,
+    /// The address of the first instruction to execute, for programs that keep data at the start
+    pub start_address: usize,
+    /// The value of the accumulator before the program starts
+    pub initial_accumulator: Option<i16>,
+    /// Values to put into RAM before the program starts, as `(address, value)` pairs
+    pub initial_ram: Option<Vec<(usize, i16)>>,
+    pub architecture: Architecture,
+    /// Allow negative values in memory cells. Without this, cells hold 0 to 999 like the
+    /// original Little Man Computer, and results below zero wrap round (e.g. -1 becomes 999) and
+    /// set a flag that makes BRZ and BRP not branch.
+    pub signed_values: bool,
+    /// Write a line of JSON to this writer after each clock cycle, describing the instruction
+    /// that was executed (see `CycleEvent`)
+    #[cfg(feature = "serde")]
+    pub event_writer: Option<Box<dyn Write>>,
+    /// Write a line of CSV to this writer after each clock cycle, describing the instruction that
+    /// was executed. The columns are the same as `TRACE_CSV_HEADER`, which isn't written.
+    pub trace_writer: Option<Box<dyn Write>>,
+    /// Record, and print, a warning when `STA` overwrites a cell that has already been run as an
+    /// instruction, which usually means the program is accidentally changing its own code
+    pub warn_self_modifying: bool,
+    /// Stop with an error once the program has output more than this many bytes, so a program
+    /// stuck in a loop can't use up all of the memory
+    pub max_output_bytes: Option<usize>,
+    /// Write the program's output to this stream as soon as it is produced, e.g. stderr to keep
+    /// it apart from the computer's state on stdout. This is ignored by `Computer::with_output`.
+    pub output_stream: Option<OutputStream>,
+    pub otc_encoding: OtcEncoding,
+    pub invalid_characters: InvalidCharacter,
+}
+
+impl Default for ComputerConfig {
+    fn default() -> Self {
+        ComputerConfig {
+            max_cycles: None,
+            output_line_width: 4,
+            trap_on_overflow: false,
+            trace: false,
+            input_source: InputSource::Interactive,
+            quiet: false,
+            color: ColorMode::Auto,
+            ram_view: RamView::Numeric,
+            ram_columns: 10,
+            start_address: 0,
+            initial_accumulator: None,
+            initial_ram: None,
+            architecture: Architecture::CLASSIC,
+            signed_values: true,
+            #[cfg(feature = "serde")]
+            event_writer: None,
+            trace_writer: None,
+            warn_self_modifying: false,
+            max_output_bytes: None,
+            output_stream: None,
+            otc_encoding: OtcEncoding::Ascii,
+            invalid_characters: InvalidCharacter::LowestByte,
+        }
+    }
+}
+
+pub struct Computer {
+    ram: Vec<i16>,
+    registers: Registers,
+    output: Output,
+    config: ComputerConfig,
+    breakpoints: HashSet<usize>,
+    /// Set when `run` stopped at a breakpoint, so the next `run` call can get past it
+    paused_at_breakpoint: bool,
+    /// The operand address of the last instruction, if it read or wrote memory
+    last_accessed_address: Option<usize>,
+    stats: ExecutionStats,
+    trace: Vec<TraceEntry>,
+    watched_addresses: HashSet<usize>,
+    watch_log: Vec<WatchEvent>,
+    input: PendingInput,
+    /// Addresses that instructions have been fetched from, if `warn_self_modifying` is enabled
+    executed_addresses: HashSet<usize>,
+    self_modifying_writes: Vec<SelfModifyingWrite>,
+}
+
+/// Text that is wrapped in an ANSI escape code when it's displayed. Nothing is allocated, so it
+/// can be printed on every cycle without slowing things down.
+struct Styled<T> {
+    text: T,
+    code: &'static str,
+    color: bool,
+}
+
+impl<T: fmt::Display> fmt::Display for Styled<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.color {
+            write!(f, "\x1b[{}m{}\x1b[0m", self.code, self.text)
+        } else {
+            write!(f, "{}", self.text)
+        }
+    }
+}
+
+/// Wraps the text in an ANSI escape code, or leaves it as it is if `color` is false
+fn style<T: fmt::Display>(text: T, code: &'static str, color: bool) -> Styled<T> {
+    Styled { text, code, color }
+}
+
+fn color_grey<T: fmt::Display>(text: T, color: bool) -> Styled<T> {
+    style(text, "90", color)
+}
+
+fn color_red<T: fmt::Display>(text: T, color: bool) -> Styled<T> {
+    style(text, "31", color)
+}
+
+fn color_yellow<T: fmt::Display>(text: T, color: bool) -> Styled<T> {
+    style(text, "33", color)
+}
+
+fn bold<T: fmt::Display>(text: T, color: bool) -> Styled<T> {
+    style(text, "1", color)
+}
+
+fn bold_underline<T: fmt::Display>(text: T, color: bool) -> Styled<T> {
+    style(text, "1;4", color)
+}
+
+/// Writes each cell along with its disassembly, going down the columns. The cell that the
+/// program counter points at is marked with a `>`, and the last cell used as an operand is
+/// shown in yellow.
+fn render_disassembled_ram(
+    f: &mut impl fmt::Write,
+    ram: &[i16],
+    program_counter: usize,
+    accessed_address: Option<usize>,
+    architecture: Architecture,
+    color: bool,
+) -> fmt::Result {
+    let columns = 5;
+    let rows = ram.len().div_ceil(columns);
+    let address_width = architecture.address_digits() as usize;
+    for row in 0..rows {
+        let line = (0..columns)
+            .map(|column| column * rows + row)
+            .filter(|&address| address < ram.len())
+            .map(|address| {
+                let cell = ram[address];
+                // Leave room for a minus sign, and for assembly like `DAT -999`
+                let text = format!(
+                    "{:0address_width$} {:>cell_width$} {:<assembly_width$}",
+                    address,
+                    cell,
+                    disassembler::disassemble_cell_for(cell, architecture),
+                    cell_width = address_width + 2,
+                    assembly_width = address_width + 6,
+                );
+                if address == program_counter {
+                    format!(">{}", bold(&text, color))
+                } else if accessed_address == Some(address) {
+                    format!(" {}", color_yellow(&text, color))
+                } else if cell == 0 {
+                    format!(" {}", color_grey(&text, color))
+                } else {
+                    format!(" {}", text)
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        writeln!(f, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+/// A value that is displayed with its digits padded with zeroes and the minus sign in front, e.g.
+/// -5 becomes `-005`, rather than `-05` like `{:03}` would give. If `sign_column` is true,
+/// positive values get a space where the minus sign would be, so that they line up with negative
+/// values.
+pub(crate) struct PaddedValue {
+    value: i16,
+    digits: usize,
+    sign_column: bool,
+}
+
+impl fmt::Display for PaddedValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.value < 0 {
+            "-"
+        } else if self.sign_column {
+            " "
+        } else {
+            ""
+        };
+        write!(
+            f,
+            "{}{:0digits$}",
+            sign,
+            self.value.unsigned_abs(),
+            digits = self.digits
+        )
+    }
+}
+
+pub(crate) fn pad_value(value: i16, digits: usize, sign_column: bool) -> PaddedValue {
+    PaddedValue {
+        value,
+        digits,
+        sign_column,
+    }
+}
+
+/// Converts a value to the ASCII character it is the code of, or `None` if it is negative or
+/// above 127
+pub fn to_ascii_char(value: i16) -> Option<char> {
+    u8::try_from(value)
+        .ok()
+        .filter(u8::is_ascii)
+        .map(char::from)
+}
+
+/// Quotes text for a CSV file if it has commas, quotes or line breaks in it
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Writes RAM as a grid, with the cell that the program counter points at in bold and underlined,
+/// and the last cell used as an operand in yellow
+fn render_ram(
+    f: &mut impl fmt::Write,
+    ram: &[i16],
+    program_counter: usize,
+    accessed_address: Option<usize>,
+    architecture: Architecture,
+    columns: usize,
+    color: bool,
+) -> fmt::Result {
+    // Cells have one more digit than addresses
+    let digits = architecture.address_digits() as usize + 1;
+    // Only make room for minus signs if they're needed, so that RAM looks the same as usual
+    // when there aren't any negative values
+    let sign_column = ram.iter().any(|&cell| cell < 0);
+    for (i, &cell) in ram.iter().enumerate() {
+        let text = pad_value(cell, digits, sign_column);
+        if i == program_counter {
+            write!(f, "{} ", bold_underline(&text, color))?;
+        } else if accessed_address == Some(i) {
+            write!(f, "{} ", color_yellow(&text, color))?;
+        } else if cell == 0 {
+            // Show in grey
+            write!(f, "{} ", color_grey(&text, color))?;
+        } else {
+            write!(f, "{} ", text)?;
+        }
+
+        if (i + 1) % columns.max(1) == 0 {
+            writeln!(f)?;
+        }
+    }
+    Ok(())
+}
+
+fn render_registers(
+    f: &mut impl fmt::Write,
+    registers: &Registers,
+    architecture: Architecture,
+    color: bool,
+) -> fmt::Result {
+    let address_width = architecture.address_digits() as usize;
+    writeln!(
+        f,
+        "PC: {}, Instruction: {}, Addr: {}, Acc: {}",
+        bold(
+            format_args!("{:0address_width$}", registers.program_counter),
+            color
+        ),
+        bold(pad_value(registers.instruction_register, 3, false), color),
+        bold(
+            format_args!("{:0address_width$}", registers.address_register),
+            color
+        ),
+        bold(
+            pad_value(registers.accumulator, address_width + 1, false),
+            color
+        )
+    )
+}
+
+fn render_output(
+    f: &mut impl fmt::Write,
+    output: &str,
+    line_width: usize,
+    color: bool,
+) -> fmt::Result {
+    // Split into "rows" of characters
+    let output_vec = output.chars().collect::<Vec<char>>();
+    let rows = output_vec.chunks(line_width.max(1));
+    // Add pipe characters to separate the rows
+    let formatted_output = rows
+        .map(|row| bold(row.iter().collect::<String>(), color).to_string())
+        .collect::<Vec<String>>()
+        .join(&color_grey("|", color).to_string());
+
+    writeln!(f, "{}", formatted_output)
+}
+
+fn render_stats(f: &mut impl fmt::Write, stats: &ExecutionStats, color: bool) -> fmt::Result {
+    let mut counts: Vec<(&str, u64)> = stats
+        .instruction_counts
+        .iter()
+        .map(|(&name, &count)| (name, count))
+        .collect();
+    // Show the most frequently executed instructions first
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let summary = counts
+        .iter()
+        .map(|(name, count)| format!("{}: {}", name, count))
+        .chain([format!("total cycles: {}", stats.total_cycles)])
+        .collect::<Vec<String>>()
+        .join(", ");
+    writeln!(f, "{}", color_grey(&summary, color))
+}
+
+/// Input that hasn't been read yet
+enum PendingInput {
+    Stdin,
+    /// Values to read before stopping with `RunError::InputExhausted`
+    Queue(VecDeque<i16>),
+    /// The state of the random number generator that makes up input values
+    Random(u64),
+}
+
+/// Makes up a value in `range`, using the SplitMix64 generator so that the same seed always gives
+/// the same values
+fn random_in(range: RangeInclusive<i16>, state: &mut u64) -> i16 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut value = *state;
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^= value >> 31;
+    let span = (*range.end() as i32 - *range.start() as i32 + 1) as u64;
+    (*range.start() as i32 + (value % span) as i32) as i16
+}
+
+/// Reads a line from stdin and returns the byte value of its first character
+fn read_character(quiet: bool) -> Option<i16> {
+    if !quiet {
+        print!("Enter a character: ");
+        io::stdout().flush().ok()?;
+    }
+    let mut line = String::new();
+    io::stdin().read_line(&mut line).ok()?;
+    // An empty line still has its newline character, so None means we've reached the end of stdin
+    line.bytes().next().map(i16::from)
+}
+
+/// Asks for a number on stdin until one in `range` is entered, returning `None` at the end of stdin
+fn read_number(range: RangeInclusive<i16>, quiet: bool, color: bool) -> Option<i16> {
+    loop {
+        if !quiet {
+            print!("Enter a number: ");
+            io::stdout().flush().ok()?;
+        }
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        match line.trim().parse::<i16>() {
+            Ok(number) if range.contains(&number) => return Some(number),
+            _ if quiet => {}
+            _ => {
+                let message = format!(
+                    "Please enter a whole number from {} to {}",
+                    range.start(),
+                    range.end()
+                );
+                println!("{}", color_red(&message, color));
+            }
+        }
+    }
+}
+
+/// Wraps a value that may be far outside the `-max_value` to `max_value` range back into it,
+/// e.g. 1000 becomes -999 and -1000 becomes 999 when `max_value` is 999
+fn wrap_overflow(integer: i32, max_value: i16) -> i16 {
+    let max_value = i32::from(max_value);
+    ((integer + max_value).rem_euclid(2 * max_value + 1) - max_value) as i16
+}
+
+/// Whether an arithmetic result is too big, or too small with signed values, to fit in a cell
+fn overflows(integer: i32, architecture: Architecture, signed: bool) -> bool {
+    if signed {
+        !i16::try_from(integer).is_ok_and(|value| architecture.value_range().contains(&value))
+    } else {
+        integer > i32::from(architecture.max_value())
+    }
+}
+
+/// Fits an arithmetic result into a cell. Without signed values, it wraps round within 0 to
+/// `max_value`, so -1 becomes 999 when `max_value` is 999.
+fn fit_result(integer: i32, max_value: i16, signed: bool) -> i16 {
+    if signed {
+        wrap_overflow(integer, max_value)
+    } else {
+        integer.rem_euclid(i32::from(max_value) + 1) as i16
+    }
+}
+
+/// Decodes the contents of a .bin file into the values of each memory cell
+pub fn bytes_to_cells(data_bytes: &[u8]) -> Vec<i16> {
+    data_bytes
+        .chunks(2)
+        .map(|bytes| {
+            // Each cell is a big-endian i16, so both bytes are needed before we know its sign
+            let high_byte = bytes[0];
+            let low_byte = bytes.get(1).copied().unwrap_or(0);
+            i16::from_be_bytes([high_byte, low_byte])
+        })
+        .collect()
+}
+
+/// Parses memory cells written as decimal integers separated by whitespace, e.g. `505 106 902`
+pub fn parse_text_cells(text: &str) -> Result<Vec<i16>, ParseCellError> {
+    parse_text_cells_for(text, Architecture::CLASSIC)
+}
+
+/// Like `parse_text_cells`, but cells can hold any value that fits in the architecture's cells
+pub fn parse_text_cells_for(
+    text: &str,
+    architecture: Architecture,
+) -> Result<Vec<i16>, ParseCellError> {
+    text.split_whitespace()
+        .map(|token| {
+            token
+                .parse::<i16>()
+                .ok()
+                .filter(|cell| architecture.value_range().contains(cell))
+                .ok_or_else(|| ParseCellError {
+                    token: token.to_string(),
+                    max_value: architecture.max_value(),
+                })
+        })
+        .collect()
+}
+
+/// Converts memory cells written as text, in the format that `parse_text_cells_for` reads, into
+/// the contents of a .bin file
+pub fn create_bin(text: &str, architecture: Architecture) -> Result<Vec<u8>, ParseCellError> {
+    Ok(cells_to_bytes(&parse_text_cells_for(text, architecture)?))
+}
+
+/// Reads lines until a blank one, so that pasted data can be split over several lines
+pub fn read_pasted_text(input: impl BufRead) -> io::Result<String> {
+    let mut text = String::new();
+    for line in input.lines() {
+        // lines() removes both \n and \r\n line endings
+        let line = line?;
+        if line.trim().is_empty() {
+            break;
+        }
+        text.push_str(&line);
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// Encodes memory cells in the .bin file format that `bytes_to_cells` reads
+pub fn cells_to_bytes(cells: &[i16]) -> Vec<u8> {
+    cells.iter().flat_map(|cell| cell.to_be_bytes()).collect()
+}
+
+impl Computer {
+    pub fn new(config: ComputerConfig) -> Self {
+        let output = match config.output_stream {
+            Some(OutputStream::Stdout) => Output::with_writer(io::stdout()),
+            Some(OutputStream::Stderr) => Output::with_writer(io::stderr()),
+            None => Output::new(),
+        };
+        Computer::with_output(config, output)
+    }
+
+    /// Creates a computer that sends its output to `output`, e.g. to capture it as it's produced
+    pub fn with_output(config: ComputerConfig, output: Output) -> Self {
+        let mut computer = Computer {
+            // RAM gets the right number of cells for the architecture when it's reset below
+            ram: Vec::new(),
+            // Let's get some registers initialised too
+            registers: Registers::default(),
+            output,
+            breakpoints: HashSet::new(),
+            paused_at_breakpoint: false,
+            last_accessed_address: None,
+            stats: ExecutionStats::default(),
+            trace: Vec::new(),
+            watched_addresses: HashSet::new(),
+            watch_log: Vec::new(),
+            input: PendingInput::Stdin,
+            executed_addresses: HashSet::new(),
+            self_modifying_writes: Vec::new(),
+            config,
+        };
+        // The starting registers, RAM and input come from the config
+        computer.reset();
+        computer
+    }
+
+    /// Assembles `source` for the config's architecture and creates a computer with the program
+    /// loaded into RAM
+    ///
+    /// ```
+    /// use rusty_man_computer::{output::OutputEvent, Computer, ComputerConfig, RunOutcome};
+    ///
+    /// let config = ComputerConfig {
+    ///     quiet: true,
+    ///     ..ComputerConfig::default()
+    /// };
+    /// let mut computer = Computer::from_assembly("LDA 3\nOUT\nHLT\nDAT 42", config).unwrap();
+    /// assert_eq!(computer.run(), Ok(RunOutcome::Halted));
+    /// assert_eq!(computer.output_events(), [OutputEvent::Int(42)]);
+    /// ```
+    pub fn from_assembly(source: &str, config: ComputerConfig) -> Result<Self, AssemblerError> {
+        let cells = assembler::assemble_for(source, config.architecture)?;
+        let mut computer = Computer::new(config);
+        computer.load_cells(&cells);
+        Ok(computer)
+    }
+
+    /// Clears RAM, the registers, the output, and everything recorded about previous runs, so the
+    /// computer can be reused. The config, breakpoints and watched addresses are kept, and the
+    /// registers, RAM and input go back to the starting values from the config.
+    pub fn reset(&mut self) {
+        self.ram = vec![0; self.config.architecture.memory_size()];
+        for &(address, value) in self.config.initial_ram.iter().flatten() {
+            // Addresses outside of RAM are ignored
+            if let Some(cell) = self.ram.get_mut(address) {
+                *cell = value;
+            }
+        }
+        let accumulator = self.config.initial_accumulator.unwrap_or_default();
+        self.registers = Registers {
+            program_counter: self.config.start_address,
+            accumulator,
+            // BRP checks the flag, so a negative starting value mustn't count as positive
+            negative_flag: accumulator < 0,
+            ..Registers::default()
+        };
+        self.output.replace_contents(String::new());
+        self.paused_at_breakpoint = false;
+        self.last_accessed_address = None;
+        self.stats = ExecutionStats::default();
+        self.trace.clear();
+        self.watch_log.clear();
+        self.executed_addresses.clear();
+        self.self_modifying_writes.clear();
+        self.input = match &self.config.input_source {
+            InputSource::Interactive => PendingInput::Stdin,
+            InputSource::Vector(values) => PendingInput::Queue(VecDeque::from(values.clone())),
+            InputSource::Seeded(seed) => PendingInput::Random(*seed),
+        };
+    }
+
+    /// Loads the values of memory cells into RAM, starting at address 0
+    pub fn load_cells(&mut self, cells: &[i16]) -> LoadReport {
+        self.load_cells_at(cells, 0)
+    }
+
+    /// Loads the values of memory cells into RAM, starting at `start_address`. Cells that would
+    /// go past the end of RAM are left out, so nothing is loaded if `start_address` isn't in RAM.
+    pub fn load_cells_at(&mut self, cells: &[i16], start_address: usize) -> LoadReport {
+        let mut touched_addresses = 0;
+        let free_cells = self.ram.get_mut(start_address..).unwrap_or_default();
+        for (ram_cell, &cell) in free_cells.iter_mut().zip(cells) {
+            *ram_cell = cell;
+            touched_addresses += 1;
+        }
+        if !self.config.quiet {
+            println!("Loaded data into {} RAM addresses", touched_addresses);
+        }
+        LoadReport {
+            cells_loaded: touched_addresses,
+            cells_truncated: cells.len() - touched_addresses,
+            odd_byte_count: false,
+        }
+    }
+
+    /// Loads several memory dumps into RAM one after the other, starting at `start_address`, so
+    /// each one carries on where the last one finished
+    pub fn load_images_at(&mut self, images: &[Vec<i16>], start_address: usize) -> LoadReport {
+        self.load_cells_at(&images.concat(), start_address)
+    }
+
+    pub fn load_data_to_ram(&mut self, data_bytes: &[u8]) -> LoadReport {
+        self.load_data_to_ram_at(data_bytes, 0)
+    }
+
+    /// Loads a memory dump into RAM starting at `start_address` instead of 0, e.g. to put data
+    /// after a program that has already been loaded
+    pub fn load_data_to_ram_at(&mut self, data_bytes: &[u8], start_address: usize) -> LoadReport {
+        LoadReport {
+            odd_byte_count: data_bytes.len() % 2 == 1,
+            ..self.load_cells_at(&bytes_to_cells(data_bytes), start_address)
+        }
+    }
+
+    /// Loads memory cells written as text, in the format that `parse_text_cells` reads. Cells can
+    /// hold any value that fits with the computer's architecture.
+    pub fn load_text_to_ram(&mut self, text: &str) -> Result<LoadReport, ParseCellError> {
+        let cells = parse_text_cells_for(text, self.config.architecture)?;
+        Ok(self.load_cells(&cells))
+    }
+
+    /// Writes every RAM cell to a file in the same format that `load_data_to_ram` reads
+    pub fn dump_ram(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, cells_to_bytes(&self.ram))?;
+        Ok(())
+    }
+
+    pub fn architecture(&self) -> Architecture {
+        self.config.architecture
+    }
+
+    pub fn accumulator(&self) -> i16 {
+        self.registers.accumulator
+    }
+
+    /// The address of the next instruction to be fetched
+    pub fn program_counter(&self) -> usize {
+        self.registers.program_counter
+    }
+
+    /// The operand address of the last instruction that was decoded
+    pub fn address_register(&self) -> usize {
+        self.registers.address_register
+    }
+
+    /// Whether the last value put into the Accumulator was below zero, before it was wrapped
+    /// round. This is what BRP checks.
+    pub fn negative_flag(&self) -> bool {
+        self.registers.negative_flag
+    }
+
+    /// The opcode of the last instruction that was decoded, e.g. `5` for `LDA`
+    pub fn instruction_register(&self) -> i16 {
+        self.registers.instruction_register
+    }
+
+    /// Puts a value into the Accumulator, e.g. from a debugger. The negative flag is set to match,
+    /// as if the value had been loaded with `LDA`.
+    pub fn set_accumulator(&mut self, value: i16) {
+        self.registers.accumulator = value;
+        self.registers.negative_flag = value < 0;
+    }
+
+    /// Makes the instruction at `address` the next one to be executed
+    pub fn set_program_counter(&mut self, address: usize) {
+        self.registers.program_counter = address;
+    }
+
+    /// Changes the contents of a memory cell. Addresses outside of RAM are ignored.
+    pub fn set_cell(&mut self, address: usize, value: i16) {
+        if let Some(cell) = self.ram.get_mut(address) {
+            *cell = value;
+        }
+    }
+
+    pub fn registers_snapshot(&self) -> RegisterSnapshot {
+        RegisterSnapshot {
+            program_counter: self.registers.program_counter,
+            instruction_register: self.registers.instruction_register,
+            address_register: self.registers.address_register,
+            accumulator: self.registers.accumulator,
+            negative_flag: self.registers.negative_flag,
+        }
+    }
+
+    pub fn snapshot(&self) -> MachineState {
+        MachineState {
+            ram: self.ram.to_vec(),
+            registers: self.registers_snapshot(),
+            output: self.output.read_all().to_string(),
+        }
+    }
+
+    /// Puts RAM, the registers and the output back to how they were when `state` was taken.
+    /// If `state` has fewer cells than RAM, the rest of RAM is cleared. Anything already sent to
+    /// the output's writer can't be taken back, so only the stored output is restored.
+    pub fn restore(&mut self, state: MachineState) {
+        self.ram = vec![0; self.config.architecture.memory_size()];
+        for (cell, value) in self.ram.iter_mut().zip(state.ram) {
+            *cell = value;
+        }
+        self.registers = Registers {
+            program_counter: state.registers.program_counter,
+            instruction_register: state.registers.instruction_register,
+            address_register: state.registers.address_register,
+            accumulator: state.registers.accumulator,
+            negative_flag: state.registers.negative_flag,
+        };
+        self.output.replace_contents(state.output);
+        self.paused_at_breakpoint = false;
+        self.last_accessed_address = None;
+        self.executed_addresses.clear();
+    }
+
+    /// Adds a value to the end of the input queue, e.g. to provide input between steps.
+    /// If input was being read from stdin or made up from a seed, only queued values will be read
+    /// from now on. A value that doesn't fit in the accumulator stops the program with
+    /// `RunError::InvalidInput` when it is read.
+    pub fn push_input(&mut self, value: i16) {
+        match &mut self.input {
+            PendingInput::Queue(queue) => queue.push_back(value),
+            _ => self.input = PendingInput::Queue(VecDeque::from([value])),
+        }
+    }
+
+    /// Each character and number the program has output so far, in order. These are cleared by
+    /// `restore`, because a `MachineState` only stores the output as text.
+    pub fn output_events(&self) -> &[OutputEvent] {
+        self.output.events()
+    }
+
+    /// Calls `listener` with each character or number the program outputs, as soon as it is
+    /// output, e.g. to show it in a GUI. The output is still stored as usual.
+    pub fn on_output(&mut self, listener: impl FnMut(OutputEvent) + 'static) {
+        self.output.set_listener(listener);
+    }
+
+    /// Makes `run` pause before the instruction at this address is executed
+    pub fn add_breakpoint(&mut self, address: usize) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: usize) {
+        self.breakpoints.remove(&address);
+    }
+
+    /// Records every instruction that reads or writes this memory address in the watch log
+    pub fn watch_address(&mut self, address: usize) {
+        self.watched_addresses.insert(address);
+    }
+
+    pub fn unwatch_address(&mut self, address: usize) {
+        self.watched_addresses.remove(&address);
+    }
+
+    pub fn watch_log(&self) -> &[WatchEvent] {
+        &self.watch_log
+    }
+
+    /// Stage 1 of a clock cycle: reads the cell that the program counter points at, and moves the
+    /// program counter on to the next address.
+    ///
+    /// `fetch`, `decode` and `execute` can be used to show each stage separately, but they don't
+    /// record execution stats, the trace or watched addresses like `step` does.
+    pub fn fetch(&mut self) -> Result<i16, RunError> {
+        let ram_index = self.registers.program_counter;
+        if ram_index >= self.ram.len() {
+            return Err(RunError::ProgramCounterOverflow);
+        }
+        self.registers.program_counter += 1;
+        Ok(self.ram[ram_index])
+    }
+
+    /// Stage 2 of a clock cycle: splits the fetched cell into the Instruction Register and the
+    /// Address Register, and works out which instruction it is
+    pub fn decode(&mut self, cell: i16) -> Result<Instruction, instruction::DecodeError> {
+        let memory_size = self.config.architecture.memory_size() as i16;
+        // Negative instructions hold the non-standard opcodes, e.g. -142 is MUL 42
+        self.registers.instruction_register = cell / memory_size;
+        self.registers.address_register = (cell % memory_size).unsigned_abs() as usize;
+        instruction::decode_for(cell, self.config.architecture)
+    }
+
+    /// Stage 3 of a clock cycle: executes the decoded instruction, which was fetched from
+    /// `instruction_location`.
+    pub fn execute(
+        &mut self,
+        instruction: Instruction,
+        instruction_location: usize,
+    ) -> Result<CycleOutcome, RunError> {
+        // Decoded instructions always fit in RAM, but ones made by hand might not
+        if let Some(operand) = instruction
+            .address()
+            .filter(|&address| address >= self.ram.len())
+        {
+            return Err(RunError::OperandOutOfRange {
+                address: instruction_location,
+                operand,
+            });
+        }
+        let ram = &mut self.ram;
+        let registers = &mut self.registers;
+        let architecture = self.config.architecture;
+        let max_value = architecture.max_value();
+        let signed = self.config.signed_values;
+        // Input can't be negative without signed values
+        let input_range = if signed {
+            architecture.value_range()
+        } else {
+            0..=max_value
+        };
+        let overflow_error = RunError::ArithmeticOverflow {
+            address: instruction_location,
+        };
+        match instruction {
+            Instruction::Halt => {
+                // HLT - Stop (Little Man has a rest)
+                if !self.config.quiet {
+                    println!("\n{}", bold("Halted!", self.config.color.is_enabled()));
+                }
+                return Ok(CycleOutcome::Halted);
+            }
+            Instruction::Add(address) => {
+                // ADD - Add the contents of the memory address to the Accumulator
+                let result = registers.accumulator as i32 + ram[address] as i32;
+                if self.config.trap_on_overflow && overflows(result, architecture, signed) {
+                    return Err(overflow_error);
+                }
+                registers.negative_flag = result < 0;
+                registers.accumulator = fit_result(result, max_value, signed);
+            }
+            Instruction::Subtract(address) => {
+                // SUB - Subtract the contents of the memory address from the Accumulator
+                let result = registers.accumulator as i32 - ram[address] as i32;
+                if self.config.trap_on_overflow && overflows(result, architecture, signed) {
+                    return Err(overflow_error);
+                }
+                registers.negative_flag = result < 0;
+                registers.accumulator = fit_result(result, max_value, signed);
+            }
+            Instruction::Store(address) => {
+                // STA or STO - Store the value in the Accumulator in the memory address given
+                ram[address] = registers.accumulator;
+            }
+            Instruction::Load(address) => {
+                // LDA - Load the Accumulator with the contents of the memory address given
+                // Without signed values, negative cells (e.g. from a .bin file) wrap round too
+                let value = ram[address];
+                registers.accumulator = if signed {
+                    value
+                } else {
+                    fit_result(i32::from(value), max_value, false)
+                };
+                registers.negative_flag = value < 0;
+            }
+            Instruction::Branch(address) => {
+                // BRA - Branch - use the address given as the address of the next instruction
+                registers.program_counter = address;
+                if !self.config.quiet {
+                    println!("BRA: Jumping to address {}", registers.program_counter)
+                }
+            }
+            Instruction::BranchIfZero(address) => {
+                // BRZ - Branch to the address given if the Accumulator is zero
+                // A result below zero that wrapped round to 0 doesn't count
+                if registers.accumulator == 0 && !registers.negative_flag {
+                    registers.program_counter = address;
+                    if !self.config.quiet {
+                        println!("BRZ: Jumping to address {}", registers.program_counter)
+                    }
+                }
+            }
+            Instruction::BranchIfPositive(address) => {
+                // BRP - Branch to the address given if the Accumulator is zero or positive
+                // This checks the negative flag rather than the Accumulator, like the original
+                // LMC, so a result that wrapped round (e.g. 999 + 1 becoming -999) still counts
+                // as positive, and 0 - 1 becoming 999 in unsigned mode doesn't
+                if !registers.negative_flag {
+                    registers.program_counter = address;
+                }
+            }
+            Instruction::Input => {
+                // INP - Take from Input
+                let input = match &mut self.input {
+                    PendingInput::Queue(queue) => queue.pop_front(),
+                    PendingInput::Random(state) => Some(random_in(input_range.clone(), state)),
+                    PendingInput::Stdin => {
+                        let color = self.config.color.is_enabled();
+                        read_number(input_range.clone(), self.config.quiet, color)
+                    }
+                };
+                let input = input.ok_or(RunError::InputExhausted)?;
+                // Only queued values can be out of range, because they haven't been checked yet
+                if !input_range.contains(&input) {
+                    return Err(RunError::InvalidInput { value: input });
+                }
+                registers.accumulator = input;
+                registers.negative_flag = registers.accumulator < 0;
+            }
+            Instruction::InputCharacter => {
+                // INC - Take a character from Input, as its ASCII code (Non-standard instruction)
+                // Queued input values are already numbers, so they are used as they are, as long as
+                // they fit in the accumulator
+                let input = match &mut self.input {
+                    PendingInput::Queue(queue) => queue.pop_front(),
+                    // Printable characters, from space to `~`
+                    PendingInput::Random(state) => Some(random_in(32..=126, state)),
+                    PendingInput::Stdin => read_character(self.config.quiet),
+                };
+                let input = input.ok_or(RunError::InputExhausted)?;
+                if !input_range.contains(&input) {
+                    return Err(RunError::InvalidInput { value: input });
+                }
+                registers.accumulator = input;
+                registers.negative_flag = registers.accumulator < 0;
+            }
+            Instruction::Output => {
+                // OUT - Copy to Output
+                self.output
+                    .push_int(registers.accumulator)
+                    .map_err(|error| RunError::OutputFailed(error.kind()))?;
+            }
+            Instruction::OutputCharacter => {
+                // OTC - Output accumulator as a character (Non-standard instruction)
+                let character = match self.config.otc_encoding {
+                    OtcEncoding::Ascii => to_ascii_char(registers.accumulator),
+                    OtcEncoding::Utf8 => u32::try_from(registers.accumulator)
+                        .ok()
+                        .and_then(char::from_u32),
+                };
+                let character = character.or(match self.config.invalid_characters {
+                    InvalidCharacter::LowestByte => Some(registers.accumulator as u8 as char),
+                    InvalidCharacter::Skip => None,
+                    InvalidCharacter::Replace => Some(char::REPLACEMENT_CHARACTER),
+                });
+                if let Some(character) = character {
+                    self.output
+                        .push_char(character)
+                        .map_err(|error| RunError::OutputFailed(error.kind()))?;
+                }
+            }
+            Instruction::NoOperation => {
+                // Cells starting with a 9 that aren't an input or output instruction do nothing
+            }
+            Instruction::Multiply(address) => {
+                // MUL - Multiply the Accumulator by the contents of the memory address (Non-standard instruction)
+                let product = registers.accumulator as i32 * ram[address] as i32;
+                if self.config.trap_on_overflow && overflows(product, architecture, signed) {
+                    return Err(overflow_error);
+                }
+                registers.negative_flag = product < 0;
+                registers.accumulator = fit_result(product, max_value, signed);
+            }
+            Instruction::Divide(address) => {
+                // DIV - Divide the Accumulator by the contents of the memory address, rounding towards zero (Non-standard instruction)
+                let divisor = ram[address];
+                if divisor == 0 {
+                    return Err(RunError::DivisionByZero {
+                        address: instruction_location,
+                    });
+                }
+                // Cells loaded from outside can hold any i16, and i16::MIN / -1 doesn't fit in one
+                let quotient = registers.accumulator as i32 / divisor as i32;
+                if self.config.trap_on_overflow && overflows(quotient, architecture, signed) {
+                    return Err(overflow_error);
+                }
+                registers.negative_flag = quotient < 0;
+                registers.accumulator = fit_result(quotient, max_value, signed);
+            }
+            Instruction::Assert(address) => {
+                // AST - Stop with an error unless the Accumulator equals the contents of the memory address (Non-standard instruction)
+                if registers.accumulator != ram[address] {
+                    return Err(RunError::AssertionFailed {
+                        address: instruction_location,
+                        expected: ram[address],
+                        actual: registers.accumulator,
+                    });
+                }
+            }
+        }
+        if let Some(limit) = self.config.max_output_bytes {
+            if self.output.read_all().len() > limit {
+                return Err(RunError::OutputLimitExceeded { limit });
+            }
+        }
+        Ok(CycleOutcome::Continued)
+    }
+
+    fn clock_cycle(&mut self) -> Result<CycleOutcome, RunError> {
+        self.paused_at_breakpoint = false;
+
+        // Stage 1: Fetch
+        let ram_index = self.registers.program_counter;
+        let instruction = self.fetch()?;
+
+        // Stage 2: Decode
+        let decoded = self.decode(instruction);
+        let mnemonic = decoded.map_or("DAT", Instruction::mnemonic);
+        *self.stats.instruction_counts.entry(mnemonic).or_default() += 1;
+        self.stats.total_cycles += 1;
+        let decoded = decoded.map_err(|_| RunError::IllegalInstruction {
+            instruction,
+            address: ram_index,
+        })?;
+        if self.config.warn_self_modifying {
+            self.executed_addresses.insert(ram_index);
+        }
+
+        // Work out whether the instruction touches a watched address before it changes anything
+        let operand_address = self.registers.address_register;
+        let memory_access = match decoded {
+            Instruction::Add(_)
+            | Instruction::Subtract(_)
+            | Instruction::Load(_)
+            | Instruction::Multiply(_)
+            | Instruction::Divide(_)
+            | Instruction::Assert(_) => Some(MemoryAccess::Read),
+            Instruction::Store(_) => Some(MemoryAccess::Write),
+            _ => None,
+        };
+        let watched_access =
+            memory_access.filter(|_| self.watched_addresses.contains(&operand_address));
+        let old_value = self.ram[operand_address];
+        let output_length = self.output.read_all().len();
+
+        // Stage 3: Execute
+        let outcome = self.execute(decoded, ram_index)?;
+        self.last_accessed_address = memory_access.map(|_| operand_address);
+        if self.config.warn_self_modifying
+            && memory_access == Some(MemoryAccess::Write)
+            && self.executed_addresses.contains(&operand_address)
+        {
+            self.self_modifying_writes.push(SelfModifyingWrite {
+                cycle: self.stats.total_cycles,
+                address: ram_index,
+                overwritten_address: operand_address,
+            });
+            if !self.config.quiet {
+                let message = format!(
+                    "Warning: STA at address {:02} overwrote the instruction at address {:02}",
+                    ram_index, operand_address
+                );
+                println!("{}", color_yellow(&message, self.config.color.is_enabled()));
+            }
+        }
+        if let Some(access) = watched_access {
+            self.watch_log.push(WatchEvent {
+                cycle: self.stats.total_cycles,
+                address: operand_address,
+                instruction,
+                access,
+                old_value,
+                new_value: self.ram[operand_address],
+            });
+        }
+        if self.config.trace {
+            self.trace.push(TraceEntry {
+                cycle: self.stats.total_cycles,
+                address: ram_index,
+                opcode: self.registers.instruction_register,
+                operand: self.registers.address_register,
+                accumulator: self.registers.accumulator,
+            });
+        }
+        if let Some(writer) = &mut self.config.trace_writer {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{}",
+                self.stats.total_cycles,
+                ram_index,
+                decoded.mnemonic(),
+                decoded
+                    .address()
+                    .map_or(String::new(), |address| address.to_string()),
+                self.registers.accumulator,
+                csv_field(&self.output.read_all()[output_length..])
+            )
+            .map_err(|error| RunError::OutputFailed(error.kind()))?;
+        }
+        #[cfg(feature = "serde")]
+        if let Some(writer) = &mut self.config.event_writer {
+            let event = CycleEvent {
+                cycle: self.stats.total_cycles,
+                program_counter: ram_index,
+                mnemonic: decoded.mnemonic().to_string(),
+                operand: decoded.address(),
+                accumulator: self.registers.accumulator,
+                output: self.output.read_all()[output_length..].to_string(),
+            };
+            serde_json::to_writer(&mut *writer, &event)
+                .map_err(io::Error::from)
+                .and_then(|()| writeln!(writer))
+                .map_err(|error| RunError::OutputFailed(error.kind()))?;
+        }
+        Ok(outcome)
+    }
+
+    /// Executes a single instruction, without printing the computer's state
+    pub fn step(&mut self) -> Result<StepResult, RunError> {
+        let program_counter_before = self.registers.program_counter;
+        let outcome = self.clock_cycle()?;
+        Ok(StepResult {
+            opcode: self.registers.instruction_register,
+            operand: self.registers.address_register,
+            program_counter_before,
+            program_counter_after: self.registers.program_counter,
+            accumulator: self.registers.accumulator,
+            halted: outcome == CycleOutcome::Halted,
+        })
+    }
+
+    pub fn execution_stats(&self) -> ExecutionStats {
+        self.stats.clone()
+    }
+
+    /// Each time the program has overwritten one of its own instructions, if
+    /// `ComputerConfig::warn_self_modifying` is enabled
+    pub fn self_modifying_writes(&self) -> &[SelfModifyingWrite] {
+        &self.self_modifying_writes
+    }
+
+    /// Every instruction executed so far, if `ComputerConfig::trace` is enabled
+    pub fn trace(&self) -> &[TraceEntry] {
+        &self.trace
+    }
+
+    /// The registers, output and RAM, as they are shown before each instruction
+    fn render_state(&self) -> String {
+        let mut state = String::new();
+        let color = self.config.color.is_enabled();
+        let architecture = self.config.architecture;
+        // Writing to a String can't fail
+        let _ = render_registers(&mut state, &self.registers, architecture, color);
+        let _ = render_output(
+            &mut state,
+            self.output.read_all(),
+            self.config.output_line_width,
+            color,
+        );
+        let _ = match self.config.ram_view {
+            RamView::Numeric => render_ram(
+                &mut state,
+                &self.ram,
+                self.registers.program_counter,
+                self.last_accessed_address,
+                architecture,
+                self.config.ram_columns,
+                color,
+            ),
+            RamView::Disassembled => render_disassembled_ram(
+                &mut state,
+                &self.ram,
+                self.registers.program_counter,
+                self.last_accessed_address,
+                architecture,
+                color,
+            ),
+        };
+        state
+    }
+
+    fn print_state(&self) {
+        if !self.config.quiet {
+            print!("\n{}", self.render_state());
+        }
+    }
+
+    fn print_stats(&self) {
+        let mut stats = String::new();
+        let _ = render_stats(&mut stats, &self.stats, self.config.color.is_enabled());
+        print!("{}", stats);
+    }
+
+    /// Runs the program until it halts, reaches a breakpoint, or reaches the configured cycle limit.
+    ///
+    /// Breakpoints are checked at the fetch stage, before the instruction at that address is
+    /// executed. Calling `run` again after a breakpoint is hit resumes from that instruction.
+    pub fn run(&mut self) -> Result<RunOutcome, RunError> {
+        let color = self.config.color.is_enabled();
+        let mut cycles: u64 = 0;
+        loop {
+            if self.config.max_cycles.is_some_and(|limit| cycles >= limit) {
+                if !self.config.quiet {
+                    let message = format!("Execution limit of {} cycles reached", cycles);
+                    println!("\n{}", color_red(&message, color));
+                    self.print_stats();
+                }
+                return Ok(RunOutcome::CycleLimitReached);
+            }
+            let address = self.registers.program_counter;
+            if !self.paused_at_breakpoint && self.breakpoints.contains(&address) {
+                if !self.config.quiet {
+                    println!(
+                        "\n{}",
+                        bold(&format!("Breakpoint hit at address {:02}", address), color)
+                    );
+                }
+                self.paused_at_breakpoint = true;
+                return Ok(RunOutcome::BreakpointHit(address));
+            }
+            self.print_state();
+            match self.clock_cycle() {
+                Ok(CycleOutcome::Continued) => {}
+                Ok(CycleOutcome::Halted) => {
+                    if !self.config.quiet {
+                        self.print_stats();
+                    }
+                    return Ok(RunOutcome::Halted);
+                }
+                Err(error) => {
+                    if !self.config.quiet {
+                        println!("\n{}", color_red(&error.to_string(), color));
+                    }
+                    return Err(error);
+                }
+            }
+            cycles += 1;
+        }
+    }
+
+    /// Runs the program as fast as possible, without printing anything, even if the computer isn't
+    /// quiet. Output still goes to the output buffer and writer. Stops when the program halts or
+    /// the configured cycle limit is reached, and breakpoints are ignored.
+    pub fn run_headless(&mut self) -> Result<RunOutcome, RunError> {
+        // Messages like "Halted!" are only printed when the computer isn't quiet
+        let quiet = mem::replace(&mut self.config.quiet, true);
+        let cycle_limit = self.config.max_cycles.unwrap_or(u64::MAX);
+        let mut outcome = Ok(RunOutcome::CycleLimitReached);
+        for _ in 0..cycle_limit {
+            match self.clock_cycle() {
+                Ok(CycleOutcome::Continued) => {}
+                Ok(CycleOutcome::Halted) => {
+                    outcome = Ok(RunOutcome::Halted);
+                    break;
+                }
+                Err(error) => {
+                    outcome = Err(error);
+                    break;
+                }
+            }
+        }
+        self.config.quiet = quiet;
+        outcome
+    }
+
+    /// Executes instructions until `condition` returns true, the program halts, or the configured
+    /// cycle limit is reached. The condition is checked before each instruction, e.g.
+    /// `|computer| computer.program_counter() == 10` stops before the instruction at address 10.
+    ///
+    /// Nothing is printed, even if the computer isn't quiet, and breakpoints are ignored.
+    pub fn run_until<F: FnMut(&Computer) -> bool>(
+        &mut self,
+        condition: F,
+    ) -> Result<RunOutcome, RunError> {
+        // Messages like "Halted!" are only printed when the computer isn't quiet
+        let quiet = mem::replace(&mut self.config.quiet, true);
+        let outcome = self.run_quietly_until(condition);
+        self.config.quiet = quiet;
+        outcome
+    }
+
+    fn run_quietly_until<F: FnMut(&Computer) -> bool>(
+        &mut self,
+        mut condition: F,
+    ) -> Result<RunOutcome, RunError> {
+        let mut cycles: u64 = 0;
+        loop {
+            if condition(self) {
+                return Ok(RunOutcome::ConditionMet);
+            }
+            if self.config.max_cycles.is_some_and(|limit| cycles >= limit) {
+                return Ok(RunOutcome::CycleLimitReached);
+            }
+            if self.clock_cycle()? == CycleOutcome::Halted {
+                return Ok(RunOutcome::Halted);
+            }
+            cycles += 1;
+        }
+    }
+}
+
+/// Runs a memory dump to completion without printing anything, and returns the program's output.
+/// Nothing is read from stdin, so programs that use INP or INC need `config.input_source` to be
+/// set.
+/// If `config.max_cycles` is reached, the output so far is returned.
+pub fn run_to_string(config: ComputerConfig, data_bytes: &[u8]) -> Result<String, RunError> {
+    let config = ComputerConfig {
+        input_source: match config.input_source {
+            InputSource::Interactive => InputSource::Vector(Vec::new()),
+            source => source,
+        },
+        quiet: true,
+        // The output is returned instead
+        output_stream: None,
+        ..config
+    };
+    let mut computer = Computer::new(config);
+    computer.load_data_to_ram(data_bytes);
+    computer.run()?;
+    Ok(computer.output.read_all().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use output::NumberSeparator;
+    use std::{cell::RefCell, rc::Rc};
+
+    fn quiet_config() -> ComputerConfig {
+        ComputerConfig {
+            quiet: true,
+            input_source: InputSource::Vector(Vec::new()),
+            ..ComputerConfig::default()
+        }
+    }
+
+    fn computer(source: &str) -> Computer {
+        Computer::from_assembly(source, quiet_config()).unwrap()
+    }
+
+    /// A writer that can still be read after it has been moved into a config or an `Output`
+    #[derive(Clone, Default)]
+    pub(crate) struct SharedWriter(pub(crate) Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedWriter {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedWriter {
+        pub(crate) fn text(&self) -> String {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+        }
+    }
+
+    #[test]
+    fn negative_initial_accumulator_sets_negative_flag() {
+        let config = ComputerConfig {
+            initial_accumulator: Some(-5),
+            ..quiet_config()
+        };
+        let mut computer = Computer::from_assembly("BRP 3\nHLT\nHLT\nHLT", config).unwrap();
+        assert!(computer.negative_flag());
+        computer.step().unwrap();
+        assert_eq!(computer.program_counter(), 1);
+    }
+
+    #[test]
+    fn setting_registers_resumes_from_breakpoint() {
+        let mut computer = computer("OUT\nOUT\nSTA 9\nHLT");
+        computer.add_breakpoint(1);
+        assert_eq!(computer.run(), Ok(RunOutcome::BreakpointHit(1)));
+        computer.set_accumulator(-5);
+        assert!(computer.negative_flag());
+        computer.set_cell(8, 7);
+        assert_eq!(computer.run(), Ok(RunOutcome::Halted));
+        assert_eq!(
+            computer.output_events(),
+            [OutputEvent::Int(0), OutputEvent::Int(-5)]
+        );
+        assert_eq!(computer.snapshot().ram[8..10], [7, -5]);
+    }
+
+    #[test]
+    fn set_program_counter_changes_next_instruction() {
+        let mut computer = computer("HLT\nOUT\nHLT");
+        computer.set_program_counter(1);
+        assert_eq!(computer.run(), Ok(RunOutcome::Halted));
+        assert_eq!(computer.output_events(), [OutputEvent::Int(0)]);
+    }
+
+    #[test]
+    fn divide_rounds_towards_zero() {
+        let mut computer = computer("LDA 4\nDIV 5\nOUT\nHLT\nDAT -7\nDAT 2");
+        computer.run().unwrap();
+        assert_eq!(computer.output_events(), [OutputEvent::Int(-3)]);
+    }
+
+    #[test]
+    fn divide_by_zero_is_an_error() {
+        let mut computer = computer("DIV 2\nHLT\nDAT 0");
+        assert_eq!(computer.run(), Err(RunError::DivisionByZero { address: 0 }));
+    }
+
+    #[test]
+    fn divide_out_of_range_value_wraps_instead_of_panicking() {
+        let mut computer = computer("DIV 2\nHLT\nDAT -1");
+        computer.set_accumulator(i16::MIN);
+        computer.run().unwrap();
+        assert_eq!(computer.accumulator(), wrap_overflow(32768, 999));
+    }
+
+    #[test]
+    fn fetch_reads_cell_and_moves_program_counter() {
+        let mut computer = computer("LDA 5\nHLT");
+        assert_eq!(computer.fetch(), Ok(505));
+        assert_eq!(computer.program_counter(), 1);
+        computer.set_program_counter(100);
+        assert_eq!(computer.fetch(), Err(RunError::ProgramCounterOverflow));
+    }
+
+    #[test]
+    fn decode_fills_instruction_and_address_registers() {
+        let mut computer = computer("HLT");
+        assert_eq!(computer.decode(542), Ok(Instruction::Load(42)));
+        assert_eq!(computer.instruction_register(), 5);
+        assert_eq!(computer.address_register(), 42);
+        assert_eq!(computer.decode(-142), Ok(Instruction::Multiply(42)));
+        assert_eq!(computer.instruction_register(), -1);
+        assert!(computer.decode(400).is_err());
+    }
+
+    #[test]
+    fn execute_runs_a_decoded_instruction() {
+        let mut computer = computer("HLT\nDAT 7");
+        assert_eq!(
+            computer.execute(Instruction::Load(1), 0),
+            Ok(CycleOutcome::Continued)
+        );
+        assert_eq!(computer.accumulator(), 7);
+        assert_eq!(
+            computer.execute(Instruction::Halt, 0),
+            Ok(CycleOutcome::Halted)
+        );
+    }
+
+    #[test]
+    fn execute_rejects_address_outside_ram() {
+        let mut computer = computer("HLT");
+        assert_eq!(
+            computer.execute(Instruction::Load(500), 3),
+            Err(RunError::OperandOutOfRange {
+                address: 3,
+                operand: 500
+            })
+        );
+    }
+
+    #[test]
+    fn run_until_stops_before_address() {
+        let config = ComputerConfig {
+            quiet: false,
+            ..quiet_config()
+        };
+        let mut computer = Computer::from_assembly("OUT\nOUT\nOUT\nHLT", config).unwrap();
+        let outcome = computer.run_until(|computer| computer.program_counter() == 2);
+        assert_eq!(outcome, Ok(RunOutcome::ConditionMet));
+        assert_eq!(computer.output_events().len(), 2);
+        assert!(!computer.config.quiet);
+        assert_eq!(computer.run_until(|_| false), Ok(RunOutcome::Halted));
+    }
+
+    #[test]
+    fn run_headless_matches_run() {
+        let program = include_bytes!("../demos/ascii_table.bin");
+        let mut run = Computer::new(quiet_config());
+        run.load_data_to_ram(program);
+        let mut headless = Computer::new(ComputerConfig {
+            quiet: false,
+            ..quiet_config()
+        });
+        headless.load_data_to_ram(program);
+        assert_eq!(run.run(), Ok(RunOutcome::Halted));
+        assert_eq!(headless.run_headless(), Ok(RunOutcome::Halted));
+        assert_eq!(run.snapshot(), headless.snapshot());
+        assert_eq!(run.output_events(), headless.output_events());
+        assert_eq!(run.execution_stats(), headless.execution_stats());
+    }
+
+    #[test]
+    fn run_headless_stops_at_cycle_limit() {
+        let config = ComputerConfig {
+            max_cycles: Some(3),
+            ..quiet_config()
+        };
+        let mut computer = Computer::from_assembly("BRA 0", config).unwrap();
+        assert_eq!(computer.run_headless(), Ok(RunOutcome::CycleLimitReached));
+        assert_eq!(computer.execution_stats().total_cycles, 3);
+    }
+
+    #[test]
+    fn run_to_string_returns_output() {
+        let config = ComputerConfig {
+            output_stream: Some(OutputStream::Stdout),
+            ..ComputerConfig::default()
+        };
+        let output = run_to_string(config, include_bytes!("../demos/ascii.bin")).unwrap();
+        let expected: String = (32..127).map(char::from).collect();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn unused_9xx_cells_do_nothing() {
+        let mut computer = computer("LDA 3\nDAT 950\nOUT\nDAT 7");
+        assert_eq!(computer.run(), Ok(RunOutcome::Halted));
+        assert_eq!(computer.output_events(), [OutputEvent::Int(7)]);
+    }
+
+    #[test]
+    fn text_cells_fit_the_architecture() {
+        assert_eq!(parse_text_cells("505 -106\n902"), Ok(vec![505, -106, 902]));
+        assert_eq!(
+            parse_text_cells("5500"),
+            Err(ParseCellError {
+                token: "5500".to_string(),
+                max_value: 999
+            })
+        );
+        let architecture = Architecture::new(3).unwrap();
+        assert_eq!(parse_text_cells_for("5500", architecture), Ok(vec![5500]));
+        let error = parse_text_cells_for("10000", architecture).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "'10000' is not a whole number from -9999 to 9999"
+        );
+    }
+
+    #[test]
+    fn from_assembly_uses_the_architecture() {
+        let config = ComputerConfig {
+            architecture: Architecture::new(3).unwrap(),
+            ..quiet_config()
+        };
+        let mut computer =
+            Computer::from_assembly("BRA 500\nORG 500\nLDA 503\nOUT\nHLT\nDAT 1234", config)
+                .unwrap();
+        assert_eq!(computer.run(), Ok(RunOutcome::Halted));
+        assert_eq!(computer.output_events(), [OutputEvent::Int(1234)]);
+    }
+
+    #[test]
+    fn pasted_text_stops_at_blank_line() {
+        let input = "505 106\r\n902\n  \n000\n";
+        assert_eq!(
+            read_pasted_text(input.as_bytes()).unwrap(),
+            "505 106\n902\n"
+        );
+    }
+
+    #[test]
+    fn create_bin_writes_loadable_file() {
+        let bytes = create_bin("505 902\n000\n-42 999", Architecture::CLASSIC).unwrap();
+        assert_eq!(bytes.len(), 10);
+        let mut computer = Computer::new(quiet_config());
+        let report = computer.load_data_to_ram(&bytes);
+        assert_eq!(report.cells_loaded, 5);
+        assert_eq!(computer.ram[..6], [505, 902, 0, -42, 999, 0]);
+
+        let error = create_bin("505 1000", Architecture::CLASSIC).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "'1000' is not a whole number from -999 to 999"
+        );
+    }
+
+    #[test]
+    fn bin_cells_round_trip() {
+        let cells = [0, 1, -1, 999, -999, 255, 256, -256, i16::MAX, i16::MIN];
+        let bytes = cells_to_bytes(&cells);
+        assert_eq!(bytes[..6], [0, 0, 0, 1, 0xFF, 0xFF]);
+        assert_eq!(bytes_to_cells(&bytes), cells);
+    }
+
+    #[test]
+    fn odd_byte_count_is_reported() {
+        assert_eq!(bytes_to_cells(&[0x03, 0x86, 0x01]), [902, 256]);
+        let mut computer = Computer::new(quiet_config());
+        let report = computer.load_data_to_ram(&[0x03, 0x86, 0x01]);
+        assert_eq!(
+            report,
+            LoadReport {
+                cells_loaded: 2,
+                cells_truncated: 0,
+                odd_byte_count: true
+            }
+        );
+    }
+
+    #[test]
+    fn loading_past_end_of_ram_is_truncated() {
+        let mut computer = Computer::new(quiet_config());
+        let report = computer.load_cells_at(&[1, 2, 3], 98);
+        assert_eq!(report.cells_loaded, 2);
+        assert_eq!(report.cells_truncated, 1);
+        assert_eq!(computer.ram[98..], [1, 2]);
+        let report = computer.load_cells_at(&[1], 100);
+        assert_eq!(report.cells_loaded, 0);
+    }
+
+    #[test]
+    fn images_are_loaded_one_after_the_other() {
+        let mut computer = Computer::new(quiet_config());
+        let report = computer.load_images_at(&[vec![901, 902, 0], vec![7, 8]], 10);
+        assert_eq!(report.cells_loaded, 5);
+        assert_eq!(computer.ram[10..13], [901, 902, 0]);
+        assert_eq!(computer.ram[13..16], [7, 8, 0]);
+        let report = computer.load_images_at(&[vec![1; 60], vec![2; 60]], 0);
+        assert_eq!(report.cells_truncated, 20);
+        assert_eq!(computer.ram[59..61], [1, 2]);
+        assert_eq!(computer.ram[99], 2);
+    }
+
+    #[test]
+    fn dump_ram_can_be_loaded_again() {
+        let mut computer = computer("LDA 4\nOUT\nHLT\nDAT -42\nDAT 999");
+        computer.set_cell(99, -1);
+        let path = std::env::temp_dir().join(format!("rmc-dump-{}.bin", std::process::id()));
+        computer.dump_ram(&path).unwrap();
+        let mut loaded = Computer::new(quiet_config());
+        let report = loaded.load_data_to_ram(&fs::read(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(report.cells_loaded, 100);
+        assert_eq!(loaded.ram, computer.ram);
+    }
+
+    #[test]
+    fn loads_text_cells() {
+        let mut computer = Computer::new(quiet_config());
+        let report = computer.load_text_to_ram("505 902\n000 42").unwrap();
+        assert_eq!(report.cells_loaded, 4);
+        assert_eq!(computer.ram[..4], [505, 902, 0, 42]);
+        assert!(computer.load_text_to_ram("505 abc").is_err());
+    }
+
+    #[test]
+    fn reads_201_and_5_byte_files() {
+        let mut computer = Computer::new(quiet_config());
+        let report = computer.load_data_to_ram(&[1; 201]);
+        assert_eq!(
+            report,
+            LoadReport {
+                cells_loaded: 100,
+                cells_truncated: 1,
+                odd_byte_count: true
+            }
+        );
+        let report = computer.load_data_to_ram(&[0x03, 0x86, 0x00, 0x2A, 0x07]);
+        assert_eq!(
+            report,
+            LoadReport {
+                cells_loaded: 3,
+                cells_truncated: 0,
+                odd_byte_count: true
+            }
+        );
+        // The dangling byte becomes the high byte of a last cell
+        assert_eq!(computer.ram[..3], [902, 42, 0x0700]);
+    }
+
+    #[test]
+    fn load_cells_runs_the_add_program() {
+        let mut computer = Computer::new(quiet_config());
+        computer.push_input(3);
+        computer.push_input(4);
+        let report = computer.load_cells(&[901, 308, 901, 108, 902, 0]);
+        assert_eq!(report.cells_loaded, 6);
+        computer.run().unwrap();
+        assert_eq!(computer.output_events(), [OutputEvent::Int(7)]);
+    }
+
+    #[test]
+    fn accumulator_getter_after_add() {
+        let mut computer = computer("LDA 3\nADD 4\nHLT\nDAT 20\nDAT 22");
+        computer.run().unwrap();
+        assert_eq!(computer.accumulator(), 42);
+        assert_eq!(computer.program_counter(), 3);
+        assert_eq!(computer.address_register(), 0);
+    }
+
+    #[test]
+    fn starts_at_the_start_address() {
+        let config = ComputerConfig {
+            start_address: 2,
+            ..quiet_config()
+        };
+        let mut computer = Computer::from_assembly("DAT 7\nDAT 8\nLDA 1\nHLT", config).unwrap();
+        assert_eq!(computer.program_counter(), 2);
+        assert_eq!(computer.fetch(), Ok(501));
+    }
+
+    #[test]
+    fn preloads_ram_and_accumulator() {
+        let config = ComputerConfig {
+            initial_accumulator: Some(30),
+            initial_ram: Some(vec![(0, 199), (99, 12)]),
+            ..quiet_config()
+        };
+        let mut computer = Computer::new(config);
+        computer.run().unwrap();
+        assert_eq!(computer.accumulator(), 42);
+    }
+
+    #[test]
+    fn inc_reads_a_character_code() {
+        let config = ComputerConfig {
+            input_source: InputSource::Vector(vec![i16::from(b'A')]),
+            ..quiet_config()
+        };
+        let mut computer = Computer::from_assembly("INC\nHLT", config).unwrap();
+        computer.run().unwrap();
+        assert_eq!(computer.accumulator(), 65);
+    }
+
+    #[test]
+    fn running_out_of_input_is_an_error() {
+        let config = ComputerConfig {
+            input_source: InputSource::Vector(vec![5]),
+            ..quiet_config()
+        };
+        let mut computer = Computer::from_assembly("INP\nINP\nHLT", config).unwrap();
+        assert_eq!(computer.run(), Err(RunError::InputExhausted));
+        assert_eq!(computer.accumulator(), 5);
+    }
+
+    #[test]
+    fn brp_doesnt_branch_after_going_below_zero() {
+        let mut computer = computer("LDA 6\nSUB 7\nBRP 5\nOUT\nHLT\nOTC\nDAT 0\nDAT 1");
+        computer.run().unwrap();
+        assert!(computer.negative_flag());
+        assert_eq!(computer.output_events(), [OutputEvent::Int(-1)]);
+    }
+
+    #[test]
+    fn opcode_4_is_an_illegal_instruction() {
+        let mut computer = computer("OUT\nDAT 400");
+        assert_eq!(
+            computer.run(),
+            Err(RunError::IllegalInstruction {
+                instruction: 400,
+                address: 1
+            })
+        );
+        let error = RunError::IllegalInstruction {
+            instruction: 400,
+            address: 1,
+        };
+        assert_eq!(error.to_string(), "Illegal instruction 400 at address 01");
+    }
+
+    #[test]
+    fn running_off_the_end_of_ram_is_an_error() {
+        let config = ComputerConfig {
+            start_address: 98,
+            initial_ram: Some(vec![(98, 902), (99, 902)]),
+            ..quiet_config()
+        };
+        let mut computer = Computer::new(config);
+        assert_eq!(computer.run(), Err(RunError::ProgramCounterOverflow));
+        assert_eq!(computer.output_events().len(), 2);
+    }
+
+    #[test]
+    fn cycle_limit_stops_runaway_programs() {
+        let config = ComputerConfig {
+            max_cycles: Some(10),
+            ..quiet_config()
+        };
+        let mut computer = Computer::from_assembly("loop BRA loop", config).unwrap();
+        assert_eq!(computer.run(), Ok(RunOutcome::CycleLimitReached));
+        assert_eq!(computer.execution_stats().total_cycles, 10);
+    }
+
+    #[test]
+    fn step_describes_the_instruction() {
+        let mut computer = computer("LDA 3\nOUT\nHLT\nDAT 7");
+        assert_eq!(
+            computer.step(),
+            Ok(StepResult {
+                opcode: 5,
+                operand: 3,
+                program_counter_before: 0,
+                program_counter_after: 1,
+                accumulator: 7,
+                halted: false
+            })
+        );
+        computer.step().unwrap();
+        assert!(computer.step().unwrap().halted);
+    }
+
+    #[test]
+    fn counts_each_instruction() {
+        let mut computer = computer("LDA 5\nloop SUB 6\nBRP loop\nOUT\nHLT\nDAT 2\nDAT 1");
+        computer.run().unwrap();
+        let stats = computer.execution_stats();
+        assert_eq!(stats.total_cycles, 9);
+        assert_eq!(stats.instruction_counts["SUB"], 3);
+        assert_eq!(stats.instruction_counts["BRP"], 3);
+        assert_eq!(stats.instruction_counts["HLT"], 1);
+    }
+
+    #[test]
+    fn multiply_and_divide() {
+        let mut calculator =
+            computer("LDA 7\nMUL 8\nOUT\nLDA 9\nDIV 10\nOUT\nHLT\nDAT 7\nDAT 6\nDAT 42\nDAT 5");
+        calculator.run().unwrap();
+        assert_eq!(
+            calculator.output_events(),
+            [OutputEvent::Int(42), OutputEvent::Int(8)]
+        );
+    }
+
+    #[test]
+    fn multiply_wraps_or_traps() {
+        let mut multiplier = computer("LDA 4\nMUL 5\nOUT\nHLT\nDAT -12\nDAT 11");
+        multiplier.run().unwrap();
+        assert_eq!(multiplier.output_events(), [OutputEvent::Int(-132)]);
+
+        let source = "LDA 4\nMUL 4\nOUT\nHLT\nDAT 100";
+        let mut computer = computer(source);
+        computer.run().unwrap();
+        assert_eq!(
+            computer.output_events(),
+            [OutputEvent::Int(wrap_overflow(10000, 999))]
+        );
+        let config = ComputerConfig {
+            trap_on_overflow: true,
+            ..quiet_config()
+        };
+        let mut computer = Computer::from_assembly(source, config).unwrap();
+        assert_eq!(
+            computer.run(),
+            Err(RunError::ArithmeticOverflow { address: 1 })
+        );
+    }
+
+    #[test]
+    fn add_and_subtract_trap_on_overflow() {
+        let config = || ComputerConfig {
+            trap_on_overflow: true,
+            ..quiet_config()
+        };
+        // 999 + 1 wraps round by default, but stops the program when trapping
+        let source = "LDA 4\nADD 5\nOUT\nHLT\nDAT 999\nDAT 1";
+        let mut wrapping = computer(source);
+        wrapping.run().unwrap();
+        assert_eq!(wrapping.output_events(), [OutputEvent::Int(-999)]);
+        let mut trapping = Computer::from_assembly(source, config()).unwrap();
+        assert_eq!(
+            trapping.run(),
+            Err(RunError::ArithmeticOverflow { address: 1 })
+        );
+
+        let mut computer = Computer::from_assembly("LDA 3\nADD 3\nHLT\nDAT 500", config()).unwrap();
+        assert_eq!(
+            computer.run(),
+            Err(RunError::ArithmeticOverflow { address: 1 })
+        );
+        let mut computer =
+            Computer::from_assembly("LDA 3\nSUB 4\nHLT\nDAT -500\nDAT 500", config()).unwrap();
+        assert_eq!(
+            computer.run(),
+            Err(RunError::ArithmeticOverflow { address: 1 })
+        );
+    }
+
+    #[test]
+    fn wraps_values_far_out_of_range() {
+        assert_eq!(wrap_overflow(990 + 21, 999), -988);
+        assert_eq!(wrap_overflow(999 + 1, 999), -999);
+        assert_eq!(wrap_overflow(-999 - 1, 999), 999);
+        assert_eq!(wrap_overflow(2997, 999), 998);
+        assert_eq!(wrap_overflow(-1000, 999), 999);
+        assert_eq!(wrap_overflow(1999, 999), 0);
+        assert_eq!(wrap_overflow(5 * 1999 + 3, 999), 3);
+        assert_eq!(wrap_overflow(-3 * 1999 - 3, 999), -3);
+        assert_eq!(wrap_overflow(i32::from(i16::MIN) * 2, 999), 431);
+    }
+
+    #[test]
+    fn unsigned_mode_wraps_to_positive_values() {
+        let config = || ComputerConfig {
+            signed_values: false,
+            ..quiet_config()
+        };
+        let source = "LDA 6\nSUB 7\nOUT\nBRP 5\nOUT\nHLT\nDAT 0\nDAT 1";
+        let mut computer = Computer::from_assembly(source, config()).unwrap();
+        computer.run().unwrap();
+        // 0 - 1 wraps round to 999, but BRP still sees that it went below zero
+        assert_eq!(
+            computer.output_events(),
+            [OutputEvent::Int(999), OutputEvent::Int(999)]
+        );
+        assert!(computer.negative_flag());
+
+        // Negative values loaded from RAM wrap round, and negative input isn't allowed
+        let source = "LDA 3\nOUT\nINP\nDAT -5";
+        let mut computer = Computer::from_assembly(source, config()).unwrap();
+        computer.push_input(-7);
+        assert_eq!(computer.run(), Err(RunError::InvalidInput { value: -7 }));
+        assert_eq!(computer.output_events(), [OutputEvent::Int(995)]);
+
+        assert_eq!(fit_result(1000, 999, false), 0);
+        assert_eq!(fit_result(-1000, 999, false), 0);
+    }
+
+    #[test]
+    fn subtracting_in_signed_and_unsigned_mode() {
+        let subtract = |signed_values| {
+            let config = ComputerConfig {
+                signed_values,
+                ..quiet_config()
+            };
+            let source = "LDA 4\nSUB 5\nOUT\nHLT\nDAT 3\nDAT 5";
+            let mut computer = Computer::from_assembly(source, config).unwrap();
+            computer.run().unwrap();
+            computer.output_events().to_vec()
+        };
+        assert_eq!(subtract(true), [OutputEvent::Int(-2)]);
+        assert_eq!(subtract(false), [OutputEvent::Int(998)]);
+    }
+
+    #[test]
+    fn breakpoints_pause_before_the_instruction() {
+        let mut computer = computer("OUT\nOUT\nOUT\nHLT");
+        computer.add_breakpoint(2);
+        assert_eq!(computer.run(), Ok(RunOutcome::BreakpointHit(2)));
+        assert_eq!(computer.output_events().len(), 2);
+        computer.remove_breakpoint(2);
+        computer.add_breakpoint(3);
+        assert_eq!(computer.run(), Ok(RunOutcome::BreakpointHit(3)));
+        assert_eq!(computer.run(), Ok(RunOutcome::Halted));
+        assert_eq!(computer.output_events().len(), 3);
+    }
+
+    #[test]
+    fn restore_goes_back_to_a_snapshot() {
+        let mut computer = computer("LDA 5\nOUT\nSTA 6\nOUT\nHLT\nDAT 3");
+        computer.step().unwrap();
+        computer.step().unwrap();
+        let state = computer.snapshot();
+        computer.run().unwrap();
+        assert_eq!(computer.snapshot().output, "33");
+        computer.restore(state.clone());
+        assert_eq!(computer.snapshot(), state);
+        assert_eq!(computer.ram[6], 0);
+        computer.run().unwrap();
+        assert_eq!(computer.snapshot().output, "33");
+    }
+
+    #[test]
+    fn reset_puts_back_the_starting_state() {
+        let config = ComputerConfig {
+            initial_accumulator: Some(5),
+            initial_ram: Some(vec![(10, 20), (200, 1)]),
+            input_source: InputSource::Vector(vec![7]),
+            ..quiet_config()
+        };
+        let mut computer =
+            Computer::from_assembly("ADD 10\nOUT\nINP\nSTA 10\nHLT", config).unwrap();
+        assert_eq!(computer.accumulator(), 5);
+        computer.run().unwrap();
+        assert_eq!(computer.ram[10], 7);
+        computer.reset();
+        assert_eq!(computer.accumulator(), 5);
+        assert_eq!(computer.program_counter(), 0);
+        assert_eq!(computer.ram[10], 20);
+        // The program has to be loaded again, but the input comes back
+        assert_eq!(computer.ram[0], 0);
+        assert!(computer.output_events().is_empty());
+        computer.load_cells(&assembler::assemble("INP\nOUT\nHLT").unwrap());
+        computer.run().unwrap();
+        assert_eq!(computer.output_events(), [OutputEvent::Int(7)]);
+    }
+
+    #[test]
+    fn input_comes_from_the_queue() {
+        let config = ComputerConfig {
+            input_source: InputSource::Vector(vec![3]),
+            ..quiet_config()
+        };
+        let mut computer = Computer::from_assembly("INP\nOUT\nINC\nOTC\nHLT", config).unwrap();
+        computer.step().unwrap();
+        computer.step().unwrap();
+        assert_eq!(computer.step(), Err(RunError::InputExhausted));
+        computer.set_program_counter(2);
+        computer.push_input(i16::from(b'h'));
+        computer.run().unwrap();
+        assert_eq!(
+            computer.output_events(),
+            [OutputEvent::Int(3), OutputEvent::Char('h')]
+        );
+    }
+
+    #[test]
+    fn queued_input_must_fit_in_the_accumulator() {
+        let mut computer = computer("INP\nINC\nHLT");
+        computer.push_input(-999);
+        computer.push_input(1000);
+        computer.step().unwrap();
+        assert_eq!(computer.accumulator(), -999);
+        assert_eq!(computer.step(), Err(RunError::InvalidInput { value: 1000 }));
+        assert_eq!(computer.accumulator(), -999);
+    }
+
+    #[test]
+    fn input_values_are_checked_when_read() {
+        let config = ComputerConfig {
+            input_source: InputSource::Vector(vec![5, -1000]),
+            ..quiet_config()
+        };
+        let mut computer = Computer::from_assembly("INP\nOUT\nINP\nOUT\nHLT", config).unwrap();
+        assert_eq!(computer.run(), Err(RunError::InvalidInput { value: -1000 }));
+        assert_eq!(computer.output_events(), [OutputEvent::Int(5)]);
+    }
+
+    #[test]
+    fn seeded_input_is_the_same_every_time() {
+        let run_with_seed = |seed| {
+            let config = ComputerConfig {
+                input_source: InputSource::Seeded(seed),
+                ..quiet_config()
+            };
+            let source = "INP\nOUT\nINP\nOUT\nINC\nOUT\nHLT";
+            let mut computer = Computer::from_assembly(source, config).unwrap();
+            computer.run().unwrap();
+            computer.output_events().to_vec()
+        };
+        let output = run_with_seed(42);
+        assert_eq!(output, run_with_seed(42));
+        assert_ne!(output, run_with_seed(43));
+        let OutputEvent::Int(character) = output[2] else {
+            panic!("expected a number, got {:?}", output[2]);
+        };
+        assert!((32..=126).contains(&character));
+    }
+
+    #[test]
+    fn trace_records_each_instruction() {
+        let config = ComputerConfig {
+            trace: true,
+            ..quiet_config()
+        };
+        let mut computer = Computer::from_assembly("LDA 2\nHLT\nDAT 9", config).unwrap();
+        computer.run().unwrap();
+        assert_eq!(
+            computer.trace(),
+            [
+                TraceEntry {
+                    cycle: 1,
+                    address: 0,
+                    opcode: 5,
+                    operand: 2,
+                    accumulator: 9
+                },
+                TraceEntry {
+                    cycle: 2,
+                    address: 1,
+                    opcode: 0,
+                    operand: 0,
+                    accumulator: 9
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_writer_gets_csv_lines() {
+        let writer = SharedWriter::default();
+        let config = ComputerConfig {
+            trace_writer: Some(Box::new(writer.clone())),
+            ..quiet_config()
+        };
+        let mut computer = Computer::from_assembly("LDA 4\nOTC\nOUT\nHLT\nDAT 44", config).unwrap();
+        computer.run().unwrap();
+        assert_eq!(
+            writer.text(),
+            "1,0,LDA,4,44,\n2,1,OTC,,44,\",\"\n3,2,OUT,,44,44\n4,3,HLT,,44,\n"
+        );
+        assert_eq!(TRACE_CSV_HEADER.split(',').count(), 6);
+    }
+
+    #[test]
+    fn watched_addresses_are_logged() {
+        let mut computer = computer("LDA 5\nADD 5\nSTA 5\nSTA 6\nHLT\nDAT 4");
+        computer.watch_address(5);
+        computer.run().unwrap();
+        let accesses: Vec<(u64, MemoryAccess, i16, i16)> = computer
+            .watch_log()
+            .iter()
+            .map(|event| (event.cycle, event.access, event.old_value, event.new_value))
+            .collect();
+        assert_eq!(
+            accesses,
+            [
+                (1, MemoryAccess::Read, 4, 4),
+                (2, MemoryAccess::Read, 4, 4),
+                (3, MemoryAccess::Write, 4, 8),
+            ]
+        );
+        assert_eq!(computer.watch_log()[2].instruction, 305);
+    }
+
+    #[test]
+    fn overwriting_instructions_is_recorded() {
+        let source = "LDA 4\nSTA 0\nSTA 5\nHLT\nDAT 903";
+        let config = ComputerConfig {
+            warn_self_modifying: true,
+            ..quiet_config()
+        };
+        let mut modifying = Computer::from_assembly(source, config).unwrap();
+        modifying.run().unwrap();
+        // Address 5 has never been run, so writing to it is fine
+        assert_eq!(
+            modifying.self_modifying_writes(),
+            [SelfModifyingWrite {
+                cycle: 2,
+                address: 1,
+                overwritten_address: 0
+            }]
+        );
+        let mut computer = computer(source);
+        computer.run().unwrap();
+        assert_eq!(computer.self_modifying_writes(), []);
+    }
+
+    #[test]
+    fn assert_instruction_checks_the_accumulator() {
+        let mut passing = computer("LDA 4\nAST 4\nOUT\nHLT\nDAT 6");
+        assert_eq!(passing.run(), Ok(RunOutcome::Halted));
+        let mut computer = computer("LDA 4\nAST 5\nOUT\nHLT\nDAT 6\nDAT 7");
+        assert_eq!(
+            computer.run(),
+            Err(RunError::AssertionFailed {
+                address: 1,
+                expected: 7,
+                actual: 6
+            })
+        );
+        assert!(computer.output_events().is_empty());
+    }
+
+    #[test]
+    fn ascii_chars_only_from_0_to_127() {
+        assert_eq!(to_ascii_char(65), Some('A'));
+        assert_eq!(to_ascii_char(0), Some('\0'));
+        assert_eq!(to_ascii_char(127), Some('\x7f'));
+        assert_eq!(to_ascii_char(128), None);
+        assert_eq!(to_ascii_char(200), None);
+        assert_eq!(to_ascii_char(-1), None);
+    }
+
+    #[test]
+    fn otc_handles_invalid_characters() {
+        let output_chars = |otc_encoding, invalid_characters| {
+            let config = ComputerConfig {
+                otc_encoding,
+                invalid_characters,
+                ..quiet_config()
+            };
+            let source = "LDA 6\nOTC\nLDA 7\nOTC\nHLT\nHLT\nDAT 233\nDAT 321";
+            let mut computer = Computer::from_assembly(source, config).unwrap();
+            computer.run().unwrap();
+            computer.snapshot().output
+        };
+        assert_eq!(
+            output_chars(OtcEncoding::Ascii, InvalidCharacter::LowestByte),
+            "éA"
+        );
+        assert_eq!(output_chars(OtcEncoding::Ascii, InvalidCharacter::Skip), "");
+        assert_eq!(
+            output_chars(OtcEncoding::Ascii, InvalidCharacter::Replace),
+            "\u{FFFD}\u{FFFD}"
+        );
+        assert_eq!(
+            output_chars(OtcEncoding::Utf8, InvalidCharacter::Skip),
+            "é\u{141}"
+        );
+    }
+
+    #[test]
+    fn output_events_from_ascii_table() {
+        let mut computer = Computer::new(quiet_config());
+        computer.load_data_to_ram(include_bytes!("../demos/ascii_table.bin"));
+        assert_eq!(computer.run(), Ok(RunOutcome::Halted));
+        let expected: Vec<OutputEvent> = (32..=96)
+            .flat_map(|code: u8| {
+                [
+                    OutputEvent::Int(i16::from(code)),
+                    OutputEvent::Char(' '),
+                    OutputEvent::Char(char::from(code)),
+                ]
+            })
+            .collect();
+        assert_eq!(computer.output_events(), expected);
+    }
+
+    #[test]
+    fn on_output_sees_each_event() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut computer = computer("LDA 4\nOUT\nOTC\nHLT\nDAT 65");
+        let listener_events = Rc::clone(&events);
+        computer.on_output(move |event| listener_events.borrow_mut().push(event));
+        computer.run().unwrap();
+        assert_eq!(*events.borrow(), computer.output_events());
+    }
+
+    #[test]
+    fn output_limit_stops_the_program() {
+        let config = ComputerConfig {
+            max_output_bytes: Some(5),
+            ..quiet_config()
+        };
+        let mut computer = Computer::from_assembly("loop OUT\nBRA loop", config).unwrap();
+        assert_eq!(
+            computer.run(),
+            Err(RunError::OutputLimitExceeded { limit: 5 })
+        );
+        assert_eq!(computer.snapshot().output, "000000");
+    }
+
+    #[test]
+    fn output_stream_gets_the_output() {
+        let writer = SharedWriter::default();
+        let mut computer = Computer::with_output(
+            quiet_config(),
+            Output::with_writer(writer.clone()).with_number_separator(NumberSeparator::Newline),
+        );
+        computer.load_cells(&assembler::assemble("LDA 5\nOUT\nOUT\nOTC\nHLT\nDAT 33").unwrap());
+        computer.run().unwrap();
+        assert_eq!(writer.text(), "33\n33!");
+    }
+
+    #[test]
+    fn never_color_mode_has_no_escape_codes() {
+        let render = |color, ram_view| {
+            let config = ComputerConfig {
+                color,
+                ram_view,
+                ..quiet_config()
+            };
+            let mut computer = Computer::from_assembly("LDA 3\nOUT\nHLT\nDAT -5", config).unwrap();
+            computer.step().unwrap();
+            computer.step().unwrap();
+            computer.render_state()
+        };
+        for ram_view in [RamView::Numeric, RamView::Disassembled] {
+            assert!(!render(ColorMode::Never, ram_view).contains('\x1b'));
+            assert!(render(ColorMode::Always, ram_view).contains('\x1b'));
+        }
+        let mut stats = String::new();
+        render_stats(&mut stats, &ExecutionStats::default(), false).unwrap();
+        assert_eq!(stats, "total cycles: 0\n");
+    }
+
+    #[test]
+    fn output_is_split_into_lines_of_8() {
+        let mut text = String::new();
+        render_output(&mut text, "Hello, world!", 8, false).unwrap();
+        assert_eq!(text, "Hello, w|orld!\n");
+        let mut text = String::new();
+        render_output(&mut text, "12345678", 8, false).unwrap();
+        assert_eq!(text, "12345678\n");
+    }
+
+    #[test]
+    fn disassembled_ram_shows_each_cell() {
+        let mut text = String::new();
+        let ram = [599, 902, 0, 42, -5];
+        render_disassembled_ram(&mut text, &ram, 1, None, Architecture::CLASSIC, false).unwrap();
+        assert_eq!(
+            text,
+            " 00  599 LDA 99   >01  902 OUT       02    0 HLT       03   42 DAT 42    04   -5 DAT -5\n"
+        );
+    }
+
+    #[test]
+    fn program_counter_and_accessed_cells_are_highlighted() {
+        let ram = [501, 902, 0, 7];
+        let mut text = String::new();
+        render_ram(&mut text, &ram, 1, Some(3), Architecture::CLASSIC, 10, true).unwrap();
+        assert_eq!(
+            text,
+            "501 \x1b[1;4m902\x1b[0m \x1b[90m000\x1b[0m \x1b[33m007\x1b[0m "
+        );
+        // Without colour, the disassembled view still marks the program counter
+        let mut text = String::new();
+        render_disassembled_ram(&mut text, &ram, 3, Some(1), Architecture::CLASSIC, false).unwrap();
+        assert!(text.contains(">03    7 DAT 7"), "{}", text);
+        assert_eq!(text.matches('>').count(), 1);
+    }
+
+    #[test]
+    fn renders_the_register_line() {
+        let registers = Registers {
+            program_counter: 7,
+            instruction_register: 5,
+            address_register: 42,
+            accumulator: -5,
+            negative_flag: true,
+        };
+        let mut line = String::new();
+        render_registers(&mut line, &registers, Architecture::CLASSIC, false).unwrap();
+        assert_eq!(line, "PC: 07, Instruction: 005, Addr: 42, Acc: -005\n");
+        let mut line = String::new();
+        render_registers(&mut line, &registers, Architecture::CLASSIC, true).unwrap();
+        assert_eq!(
+            line,
+            "PC: \x1b[1m07\x1b[0m, Instruction: \x1b[1m005\x1b[0m, Addr: \x1b[1m42\x1b[0m, Acc: \x1b[1m-005\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn ram_rows_with_negative_values_line_up() {
+        let ram = [5, -5, 0, 999, -999, 12];
+        let mut text = String::new();
+        render_ram(&mut text, &ram, 10, None, Architecture::CLASSIC, 3, false).unwrap();
+        assert_eq!(text, " 005 -005  000 \n 999 -999  012 \n");
+        let mut text = String::new();
+        render_ram(
+            &mut text,
+            &[5, 0, 42],
+            10,
+            None,
+            Architecture::CLASSIC,
+            3,
+            false,
+        )
+        .unwrap();
+        assert_eq!(text, "005 000 042 \n");
+    }
+
+    #[test]
+    fn pads_values_with_sign_in_front() {
+        assert_eq!(pad_value(-5, 3, false).to_string(), "-005");
+        assert_eq!(pad_value(5, 3, false).to_string(), "005");
+        assert_eq!(pad_value(5, 3, true).to_string(), " 005");
+        assert_eq!(pad_value(-999, 3, true).to_string(), "-999");
+    }
+
+    #[test]
+    fn architecture_sizes() {
+        assert_eq!(Architecture::default(), Architecture::CLASSIC);
+        assert_eq!(Architecture::CLASSIC.memory_size(), 100);
+        assert_eq!(Architecture::CLASSIC.max_value(), 999);
+        let architecture = Architecture::new(3).unwrap();
+        assert_eq!(architecture.memory_size(), 1000);
+        assert_eq!(architecture.max_value(), 9999);
+        assert_eq!(architecture.value_range(), -9999..=9999);
+        assert_eq!(Architecture::new(0), None);
+        assert_eq!(Architecture::new(4), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn machine_state_json_round_trip() {
+        let source = "LDA 5\nOUT\nADD 5\nOUT\nHLT\nDAT -21";
+        let mut computer = computer(source);
+        computer.step().unwrap();
+        computer.step().unwrap();
+        let state = computer.snapshot();
+        let json = serde_json::to_string(&state).unwrap();
+        let loaded = serde_json::from_str::<MachineState>(&json).unwrap();
+        assert_eq!(loaded, state);
+        computer.run().unwrap();
+        assert_eq!(computer.snapshot().output, "-21-42");
+
+        // Carrying on from the loaded state finishes the same way
+        let mut restored = Computer::new(quiet_config());
+        restored.restore(loaded);
+        assert_eq!(restored.run(), Ok(RunOutcome::Halted));
+        assert_eq!(restored.snapshot(), computer.snapshot());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn event_writer_gets_json_lines() {
+        let writer = SharedWriter::default();
+        let config = ComputerConfig {
+            event_writer: Some(Box::new(writer.clone())),
+            ..quiet_config()
+        };
+        let mut computer = Computer::from_assembly("LDA 2\nOUT\nDAT 7", config).unwrap();
+        computer.step().unwrap();
+        computer.step().unwrap();
+        let events: Vec<CycleEvent> = writer
+            .text()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            events,
+            [
+                CycleEvent {
+                    cycle: 1,
+                    program_counter: 0,
+                    mnemonic: "LDA".to_string(),
+                    operand: Some(2),
+                    accumulator: 7,
+                    output: String::new()
+                },
+                CycleEvent {
+                    cycle: 2,
+                    program_counter: 1,
+                    mnemonic: "OUT".to_string(),
+                    operand: None,
+                    accumulator: 7,
+                    output: "7".to_string()
+                },
+            ]
+        );
+    }
+}
@@ -1,202 +1,621 @@
-use std::{env, error::Error, fs};
+use rusty_man_computer::{
+    assembler::{assemble_with_warnings_for, listing_for},
+    bytes_to_cells, cells_to_bytes, create_bin, monitor, parse_text_cells_for, read_pasted_text,
+    validator::validate,
+    Architecture, ColorMode, Computer, ComputerConfig, InputSource, InvalidCharacter, OtcEncoding,
+    OutputStream, RamView, RunOutcome, TRACE_CSV_HEADER,
+};
+use std::{
+    env,
+    error::Error,
+    fs::{self, File},
+    io::{self, LineWriter, Read, Write},
+    path::{Path, PathBuf},
+    process,
+};
 
-type RAM = [i16; 100];
-
-struct Registers {
-    program_counter: usize,
-    instruction_register: i16,
-    address_register: usize,
-    accumulator: i16,
-}
-
-fn color_grey(text: &str) -> String {
-    format!("\x1b[90m{}\x1b[0m", text)
+struct ExecuteArgs {
+    /// Memory dumps to load into RAM, one after the other
+    ram_files: Vec<PathBuf>,
+    /// The address to start loading the memory dumps at
+    ram_offset: usize,
+    dump_ram: Option<PathBuf>,
+    /// A CSV file to write each executed instruction to
+    trace_file: Option<PathBuf>,
+    config: ComputerConfig,
 }
 
-fn bold(text: &str) -> String {
-    format!("\x1b[1m{}\x1b[0m", text)
+enum Command {
+    /// Runs a memory dump (.bin file) on the computer
+    Execute(ExecuteArgs),
+    /// Assembles a program and runs it straight away, without writing a .bin file
+    Run { program: PathBuf, args: ExecuteArgs },
+    /// Loads a memory dump and lets the user control the computer by typing commands
+    Monitor(ExecuteArgs),
+    /// Assembles a program into a .bin file, and optionally writes a listing of where each
+    /// instruction ended up
+    Assemble {
+        program: PathBuf,
+        output: PathBuf,
+        listing: Option<PathBuf>,
+        architecture: Architecture,
+    },
+    /// Converts memory cells written as numbers into a .bin file. If there is no text file, the
+    /// numbers are pasted in instead.
+    CreateBin {
+        text_file: Option<PathBuf>,
+        output: PathBuf,
+        architecture: Architecture,
+    },
 }
 
-fn print_ram(ram: &RAM) {
-    let columns = 10;
-    for (i, &cell) in ram.iter().enumerate() {
-        if cell == 0 {
-            // Print in grey
-            print!("{} ", color_grey("000"));
-        } else {
-            print!("{:#03} ", cell);
-        }
-
-        if (i + 1) % columns == 0 {
-            println!();
+fn parse_execute_args(
+    mut args: impl Iterator<Item = String>,
+) -> Result<ExecuteArgs, Box<dyn Error>> {
+    let mut parsed = ExecuteArgs {
+        ram_files: Vec::new(),
+        ram_offset: 0,
+        dump_ram: None,
+        trace_file: None,
+        config: ComputerConfig::default(),
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dump-ram" => {
+                let path = args.next().ok_or("--dump-ram requires a file path")?;
+                parsed.dump_ram = Some(PathBuf::from(path));
+            }
+            "--ram" => {
+                let path = args.next().ok_or("--ram requires a file path")?;
+                parsed.ram_files.push(PathBuf::from(path));
+            }
+            "--ram-offset" => {
+                let address = args.next().ok_or("--ram-offset requires an address")?;
+                parsed.ram_offset = address
+                    .parse()
+                    .map_err(|_| format!("Invalid RAM offset: {}", address))?;
+            }
+            "--trace-file" => {
+                let path = args.next().ok_or("--trace-file requires a file path")?;
+                parsed.trace_file = Some(PathBuf::from(path));
+            }
+            "--max-cycles" => {
+                let limit = args.next().ok_or("--max-cycles requires a number")?;
+                let limit = limit
+                    .parse()
+                    .map_err(|_| format!("Invalid cycle limit: {}", limit))?;
+                parsed.config.max_cycles = Some(limit);
+            }
+            "--max-output-bytes" => {
+                let limit = args.next().ok_or("--max-output-bytes requires a number")?;
+                let limit = limit
+                    .parse()
+                    .map_err(|_| format!("Invalid output limit: {}", limit))?;
+                parsed.config.max_output_bytes = Some(limit);
+            }
+            "--random-input" => {
+                let seed = args.next().ok_or("--random-input requires a seed")?;
+                let seed = seed
+                    .parse()
+                    .map_err(|_| format!("Invalid seed: {}", seed))?;
+                parsed.config.input_source = InputSource::Seeded(seed);
+            }
+            "--line-width" => {
+                let width = args.next().ok_or("--line-width requires a number")?;
+                let width = width
+                    .parse()
+                    .ok()
+                    .filter(|&width| width > 0)
+                    .ok_or(format!("Invalid line width: {}", width))?;
+                parsed.config.output_line_width = width;
+            }
+            "--ram-columns" => {
+                let columns = args.next().ok_or("--ram-columns requires a number")?;
+                parsed.config.ram_columns = columns
+                    .parse()
+                    .ok()
+                    .filter(|&columns| columns > 0)
+                    .ok_or(format!("Invalid number of RAM columns: {}", columns))?;
+            }
+            "--start" => {
+                let address = args.next().ok_or("--start requires an address")?;
+                parsed.config.start_address = address
+                    .parse()
+                    .map_err(|_| format!("Invalid start address: {}", address))?;
+            }
+            "--address-digits" => parsed.config.architecture = parse_architecture(args.next())?,
+            "--quiet" => parsed.config.quiet = true,
+            "--unsigned" => parsed.config.signed_values = false,
+            "--warn-self-modifying" => parsed.config.warn_self_modifying = true,
+            "--color" => {
+                let mode = args
+                    .next()
+                    .ok_or("--color requires auto, always or never")?;
+                parsed.config.color = match mode.as_str() {
+                    "auto" => ColorMode::Auto,
+                    "always" => ColorMode::Always,
+                    "never" => ColorMode::Never,
+                    _ => return Err(format!("Invalid color mode: {}", mode).into()),
+                };
+            }
+            "--ram-view" => {
+                let view = args
+                    .next()
+                    .ok_or("--ram-view requires numeric or disassembled")?;
+                parsed.config.ram_view = match view.as_str() {
+                    "numeric" => RamView::Numeric,
+                    "disassembled" => RamView::Disassembled,
+                    _ => return Err(format!("Invalid RAM view: {}", view).into()),
+                };
+            }
+            "--output-stream" => {
+                let stream = args
+                    .next()
+                    .ok_or("--output-stream requires stdout or stderr")?;
+                parsed.config.output_stream = match stream.as_str() {
+                    "stdout" => Some(OutputStream::Stdout),
+                    "stderr" => Some(OutputStream::Stderr),
+                    _ => return Err(format!("Invalid output stream: {}", stream).into()),
+                };
+            }
+            "--otc-encoding" => {
+                let encoding = args.next().ok_or("--otc-encoding requires ascii or utf8")?;
+                parsed.config.otc_encoding = match encoding.as_str() {
+                    "ascii" => OtcEncoding::Ascii,
+                    "utf8" => OtcEncoding::Utf8,
+                    _ => return Err(format!("Invalid OTC encoding: {}", encoding).into()),
+                };
+            }
+            "--invalid-characters" => {
+                let action = args
+                    .next()
+                    .ok_or("--invalid-characters requires byte, skip or replace")?;
+                parsed.config.invalid_characters = match action.as_str() {
+                    "byte" => InvalidCharacter::LowestByte,
+                    "skip" => InvalidCharacter::Skip,
+                    "replace" => InvalidCharacter::Replace,
+                    _ => return Err(format!("Invalid character handling: {}", action).into()),
+                };
+            }
+            flag if flag.starts_with("--") => {
+                return Err(format!("Unknown option: {}", flag).into());
+            }
+            _ => parsed.ram_files.push(PathBuf::from(arg)),
         }
     }
+    // This can only be checked once we know how big memory is
+    if parsed.config.start_address >= parsed.config.architecture.memory_size() {
+        return Err(format!("Invalid start address: {}", parsed.config.start_address).into());
+    }
+    if parsed.ram_offset >= parsed.config.architecture.memory_size() {
+        return Err(format!("Invalid RAM offset: {}", parsed.ram_offset).into());
+    }
+    Ok(parsed)
 }
 
-fn print_registers(registers: &Registers) {
-    println!(
-        "PC: {}, Instruction: {}, Addr: {}, Acc: {}",
-        bold(&format!("{:02}", registers.program_counter)),
-        bold(&format!("{:03}", registers.instruction_register)),
-        bold(&format!("{:02}", registers.address_register)),
-        bold(&format!("{:03}", registers.accumulator))
-    );
+/// Parses the value given after `--address-digits`
+fn parse_architecture(digits: Option<String>) -> Result<Architecture, Box<dyn Error>> {
+    let digits = digits.ok_or("--address-digits requires a number")?;
+    let architecture = digits
+        .parse()
+        .ok()
+        .and_then(Architecture::new)
+        .ok_or(format!("Invalid number of address digits: {}", digits))?;
+    Ok(architecture)
 }
 
-fn print_output(output: &String) {
-    // Split into "rows" of 3 characters
-    let output_vec = output.chars().collect::<Vec<char>>();
-    let rows = output_vec.chunks(4);
-    // Add pipe characters to separate the rows
-    let formatted_output = rows
-        .map(|row| bold(&row.iter().collect::<String>()))
-        .collect::<Vec<String>>()
-        .join(&color_grey("|"));
+fn parse_assemble_args(mut args: impl Iterator<Item = String>) -> Result<Command, Box<dyn Error>> {
+    let mut program = None;
+    let mut output = None;
+    let mut listing = None;
+    let mut architecture = Architecture::CLASSIC;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                let path = args.next().ok_or("--output requires a file path")?;
+                output = Some(PathBuf::from(path));
+            }
+            "--listing" => {
+                let path = args.next().ok_or("--listing requires a file path")?;
+                listing = Some(PathBuf::from(path));
+            }
+            "--address-digits" => architecture = parse_architecture(args.next())?,
+            flag if flag.starts_with('-') => {
+                return Err(format!("Unknown option: {}", flag).into());
+            }
+            _ => program = Some(PathBuf::from(arg)),
+        }
+    }
+    let program: PathBuf = program.ok_or("Please provide the filename of a program to assemble")?;
+    // By default, put the .bin file next to the program
+    let output = output.unwrap_or_else(|| program.with_extension("bin"));
+    Ok(Command::Assemble {
+        program,
+        output,
+        listing,
+        architecture,
+    })
+}
 
-    println!("{}", formatted_output);
+fn parse_create_bin_args(
+    mut args: impl Iterator<Item = String>,
+) -> Result<Command, Box<dyn Error>> {
+    let mut text_file = None;
+    let mut output = None;
+    let mut architecture = Architecture::CLASSIC;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => {
+                let path = args.next().ok_or("--from requires a file path")?;
+                text_file = Some(PathBuf::from(path));
+            }
+            "--address-digits" => architecture = parse_architecture(args.next())?,
+            "-o" | "--output" => {
+                let path = args.next().ok_or("--output requires a file path")?;
+                output = Some(PathBuf::from(path));
+            }
+            _ => return Err(format!("Unknown option: {}", arg).into()),
+        }
+    }
+    let output = output.ok_or("Please provide a filename to write the binary data to")?;
+    Ok(Command::CreateBin {
+        text_file,
+        output,
+        architecture,
+    })
 }
 
-fn check_overflow(integer: &mut i16) {
-    let positive_overflow = *integer - 999;
-    if positive_overflow > 0 {
-        *integer = -1000 + positive_overflow;
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Command, Box<dyn Error>> {
+    let mut args = args.peekable();
+    if args.next_if_eq("assemble").is_some() {
+        return parse_assemble_args(args);
+    }
+    if args.next_if_eq("create-bin").is_some() {
+        return parse_create_bin_args(args);
     }
-    let negative_overflow = *integer + 999;
-    if negative_overflow < 0 {
-        *integer = 1000 + negative_overflow;
+    if args.next_if_eq("run").is_some() {
+        let mut args = parse_execute_args(args)?;
+        if args.ram_files.len() != 1 {
+            return Err("Please provide the filename of one program to run".into());
+        }
+        let program = args.ram_files.remove(0);
+        return Ok(Command::Run { program, args });
+    }
+    if args.next_if_eq("monitor").is_some() {
+        return Ok(Command::Monitor(parse_execute_args(args)?));
     }
+    Ok(Command::Execute(parse_execute_args(args)?))
 }
 
-fn execute_instruction(ram: &mut RAM, registers: &mut Registers, output: &mut String) -> bool {
-    match registers.instruction_register {
-        0 => {
-            // HLT - Stop (Little Man has a rest)
-            println!("\n{}", bold("Halted!"));
-            return false;
-        }
-        1 => {
-            // ADD - Add the contents of the memory address to the Accumulator
-            registers.accumulator += ram[registers.address_register];
-            check_overflow(&mut registers.accumulator);
+fn assemble_program(
+    program: PathBuf,
+    output: PathBuf,
+    listing_file: Option<PathBuf>,
+    architecture: Architecture,
+) -> Result<(), Box<dyn Error>> {
+    let source = fs::read_to_string(&program)?;
+    let machine_code = match assemble_with_warnings_for(&source, architecture) {
+        Ok((machine_code, warnings)) => {
+            for warning in warnings {
+                println!("Warning: {}", warning);
+            }
+            machine_code
         }
-        2 => {
-            // SUB - Subtract the contents of the memory address from the Accumulator
-            registers.accumulator -= ram[registers.address_register];
-            check_overflow(&mut registers.accumulator);
+        Err(error) => {
+            eprintln!("Error: {}", error.render(&source));
+            process::exit(1);
         }
-        3 => {
-            // STA or STO - Store the value in the Accumulator in the memory address given
-            ram[registers.address_register] = registers.accumulator;
+    };
+    fs::write(&output, cells_to_bytes(&machine_code))?;
+    if let Some(listing_file) = listing_file {
+        fs::write(listing_file, listing_for(&source, architecture)?)?;
+    }
+    println!(
+        "Assembled {} into {} memory cells in {}",
+        program.display(),
+        machine_code.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn create_bin_file(
+    text_file: Option<PathBuf>,
+    output: PathBuf,
+    architecture: Architecture,
+) -> Result<(), Box<dyn Error>> {
+    let text = match text_file {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            println!("Paste in the memory data, then press Enter on a blank line:");
+            read_pasted_text(io::stdin().lock())?
         }
-        4 => {
-            // This code is unused and gives an error
-            panic!("Opcode 4 is not allowed!");
+    };
+    match create_bin(&text, architecture) {
+        Ok(bytes) => {
+            fs::write(&output, &bytes)?;
+            println!(
+                "Wrote {} memory cells to {}",
+                bytes.len() / 2,
+                output.display()
+            );
+            Ok(())
         }
-        5 => {
-            // LDA - Load the Accumulator with the contents of the memory address given
-            registers.accumulator = ram[registers.address_register];
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            process::exit(1);
         }
-        6 => {
-            // BRA - Branch - use the address given as the address of the next instruction
-            registers.program_counter = registers.address_register;
-            println!("BRA: Jumping to address {}", registers.program_counter)
+    }
+}
+
+/// Reads the memory cells from a memory dump (.bin file), a text file, or stdin if the filename is
+/// `-`. Also returns whether the memory dump had an odd number of bytes.
+fn read_ram_file(
+    filename: &Path,
+    architecture: Architecture,
+) -> Result<(Vec<i16>, bool), Box<dyn Error>> {
+    if filename.as_os_str() == "-" {
+        // Read the memory dump from stdin instead. If stdin is empty, no cells are loaded.
+        // Programs can't read any input from stdin after this.
+        let mut data = Vec::new();
+        io::stdin().read_to_end(&mut data)?;
+        Ok((bytes_to_cells(&data), data.len() % 2 == 1))
+    } else if filename
+        .extension()
+        .is_some_and(|extension| extension == "txt")
+    {
+        // Text files can be used instead, with each cell written as a number
+        let text = fs::read_to_string(filename)?;
+        let cells = parse_text_cells_for(&text, architecture).map_err(|error| error.to_string())?;
+        Ok((cells, false))
+    } else {
+        let data = fs::read(filename)?;
+        Ok((bytes_to_cells(&data), data.len() % 2 == 1))
+    }
+}
+
+/// Reads memory dumps and loads them into RAM one after the other, starting at `offset`. Warns
+/// about any problems with them.
+fn load_ram_files(
+    computer: &mut Computer,
+    filenames: &[PathBuf],
+    offset: usize,
+    quiet: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut images = Vec::new();
+    for filename in filenames {
+        let (cells, odd_byte_count) = read_ram_file(filename, computer.architecture())?;
+        if odd_byte_count && !quiet {
+            println!(
+                "Warning: {} has an odd number of bytes, so its last cell is incomplete",
+                filename.display()
+            );
         }
-        7 => {
-            // BRZ - Branch to the address given if the Accumulator is zero
-            if registers.accumulator == 0 {
-                registers.program_counter = registers.address_register;
-                println!("BRZ: Jumping to address {}", registers.program_counter)
-            }
+        images.push(cells);
+    }
+    let report = computer.load_images_at(&images, offset);
+
+    if !quiet {
+        if report.cells_truncated > 0 {
+            println!(
+                "Warning: The memory dump is {} cells too long to fit in RAM, so the extra cells were ignored",
+                report.cells_truncated
+            );
         }
-        8 => {
-            // BRP - Branch to the address given if the Accumulator is zero or positive
-            if registers.accumulator >= 0 {
-                registers.program_counter = registers.address_register;
-            }
+        // Check the program for obvious mistakes before running it
+        let program = &computer.snapshot().ram[..offset + report.cells_loaded];
+        for warning in validate(program, computer.program_counter(), computer.architecture()) {
+            println!("Warning: {}", warning);
         }
-        9 => {
-            if registers.address_register == 1 {
-                // INP - Take from Input
-                // TODO
-            }
-            if registers.address_register == 2 {
-                // OUT - Copy to Output
-                output.push_str(format!("{}", registers.accumulator).as_str());
-            }
-            if registers.address_register == 22 {
-                // OTC - Output accumulator as a character (Non-standard instruction)
-                output.push(registers.accumulator as u8 as char);
+    }
+    Ok(())
+}
+
+/// Makes the computer write each instruction it executes to a CSV file
+fn open_trace_file(config: &mut ComputerConfig, path: &Path) -> io::Result<()> {
+    // Write each line straight away, so the trace is complete even if the program fails
+    let mut file = LineWriter::new(File::create(path)?);
+    writeln!(file, "{}", TRACE_CSV_HEADER)?;
+    config.trace_writer = Some(Box::new(file));
+    Ok(())
+}
+
+/// Assembles a program and runs it on the computer
+fn run_program(program: &Path, args: ExecuteArgs) -> Result<(), Box<dyn Error>> {
+    let source = fs::read_to_string(program)?;
+    match assemble_with_warnings_for(&source, args.config.architecture) {
+        Ok((machine_code, warnings)) => {
+            if !args.config.quiet {
+                for warning in warnings {
+                    println!("Warning: {}", warning);
+                }
             }
+            execute(args, Some(machine_code))
         }
-        _ => {
-            panic!("Unhandled opcode: {}", registers.instruction_register);
+        Err(error) => {
+            eprintln!("Error: {}", error.render(&source));
+            process::exit(1);
         }
     }
-    true
 }
 
-fn clock_cycle(ram: &mut RAM, registers: &mut Registers, output: &mut String) -> bool {
-    // Stage 1: Fetch
-    let ram_index = registers.program_counter;
-    registers.program_counter += 1;
+/// Runs the computer, with RAM loaded from the memory dumps in `args` or from `machine_code`
+fn execute(args: ExecuteArgs, machine_code: Option<Vec<i16>>) -> Result<(), Box<dyn Error>> {
+    let mut config = args.config;
+    let quiet = config.quiet;
+    let max_cycles = config.max_cycles;
+    if quiet {
+        // The computer's state isn't printed, so print the program's output as it's produced
+        config.output_stream.get_or_insert(OutputStream::Stdout);
+    } else {
+        println!("Little Man Computer implemented in Rust!");
+    }
+    if let Some(path) = &args.trace_file {
+        open_trace_file(&mut config, path)?;
+    }
+    let mut computer = Computer::new(config);
 
-    // Stage 2: Decode
-    let instruction = ram[ram_index];
-    let instruction_code = instruction / 100;
-    let instruction_address = instruction % 100;
-    registers.instruction_register = instruction_code;
-    registers.address_register = instruction_address as usize;
+    if let Some(machine_code) = machine_code {
+        computer.load_cells(&machine_code);
+    } else if !args.ram_files.is_empty() {
+        load_ram_files(&mut computer, &args.ram_files, args.ram_offset, quiet)?;
+    }
 
-    // Stage 3: Execute
-    execute_instruction(ram, registers, output)
-}
+    let result = computer.run();
 
-fn load_data_to_ram(ram: &mut RAM, data_bytes: Vec<u8>) {
-    let mut touched_addresses = 0;
-    for (i, &byte) in data_bytes.iter().enumerate() {
-        if i >= ram.len() * 2 {
-            break;
+    if let Some(path) = &args.dump_ram {
+        computer.dump_ram(path)?;
+        if !quiet {
+            println!("Dumped RAM to {}", path.display());
         }
-        let target_address = i / 2;
-        if i % 2 == 0 {
-            ram[target_address] = (byte as i16) << 8;
-            touched_addresses += 1;
-        } else {
-            ram[target_address] += byte as i16;
+    }
+
+    match result {
+        Ok(RunOutcome::CycleLimitReached) => {
+            // The limit has already been printed by run(), unless it's in quiet mode
+            if quiet {
+                eprintln!(
+                    "Error: Execution limit of {} cycles reached",
+                    max_cycles.unwrap_or_default()
+                );
+            }
+            process::exit(1);
         }
+        Err(error) => {
+            // The error has already been printed by run(), unless it's in quiet mode
+            if quiet {
+                eprintln!("Error: {}", error);
+            }
+            process::exit(1);
+        }
+        Ok(_) => Ok(()),
+    }
+}
+
+fn start_monitor(args: ExecuteArgs) -> Result<(), Box<dyn Error>> {
+    let mut config = args.config;
+    let quiet = config.quiet;
+    if let Some(path) = &args.trace_file {
+        open_trace_file(&mut config, path)?;
+    }
+    let mut computer = Computer::new(config);
+    if !args.ram_files.is_empty() {
+        load_ram_files(&mut computer, &args.ram_files, args.ram_offset, quiet)?;
     }
-    println!("Loaded data into {} RAM addresses", touched_addresses);
+    println!("Type 'help' for a list of commands");
+    monitor::run(&mut computer)?;
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    println!("Little Man Computer implemented in Rust!");
-    // Array of 100 i16 ints
-    let mut ram: RAM = [0; 100];
-    // Let's get some registers initialised too
-    let mut registers = Registers {
-        program_counter: 0,
-        instruction_register: 0,
-        address_register: 0,
-        accumulator: 0,
-    };
-    let mut output = String::new();
+    match parse_args(env::args().skip(1))? {
+        Command::Execute(args) => execute(args, None),
+        Command::Run { program, args } => run_program(&program, args),
+        Command::Monitor(args) => start_monitor(args),
+        Command::Assemble {
+            program,
+            output,
+            listing,
+            architecture,
+        } => assemble_program(program, output, listing, architecture),
+        Command::CreateBin {
+            text_file,
+            output,
+            architecture,
+        } => create_bin_file(text_file, output, architecture),
+    }
+}
 
-    // If a memory dump (.bin file) has been provided, load it into RAM
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 1 {
-        let filename = &args[1];
-        let data = fs::read(filename)?;
-        load_data_to_ram(&mut ram, data);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> impl Iterator<Item = String> + '_ {
+        line.split_whitespace().map(String::from)
     }
 
-    let mut should_continue = true;
-    while should_continue {
-        println!();
-        print_registers(&registers);
-        print_output(&output);
-        print_ram(&ram);
-        should_continue = clock_cycle(&mut ram, &mut registers, &mut output);
+    #[test]
+    fn parses_execute_options() {
+        let Ok(Command::Execute(parsed)) = parse_args(args(
+            "demo.bin --ram data.bin --ram-offset 50 --address-digits 3 --start 500 --quiet",
+        )) else {
+            panic!("expected the execute command");
+        };
+        assert_eq!(
+            parsed.ram_files,
+            [PathBuf::from("demo.bin"), PathBuf::from("data.bin")]
+        );
+        assert_eq!(parsed.ram_offset, 50);
+        assert_eq!(parsed.config.architecture, Architecture::new(3).unwrap());
+        assert_eq!(parsed.config.start_address, 500);
+        assert!(parsed.config.quiet);
     }
 
-    Ok(())
+    #[test]
+    fn rejects_bad_execute_options() {
+        for line in [
+            "--start 100",
+            "--ram-offset 100",
+            "--address-digits 4",
+            "--max-cycles lots",
+            "--line-width 0",
+            "--frobnicate",
+            "--ram",
+        ] {
+            assert!(parse_args(args(line)).is_err(), "parsing {}", line);
+        }
+    }
+
+    #[test]
+    fn parses_assemble_options() {
+        let Ok(Command::Assemble {
+            program,
+            output,
+            listing,
+            architecture,
+        }) = parse_args(args("assemble add.lmc --listing add.lst"))
+        else {
+            panic!("expected the assemble command");
+        };
+        assert_eq!(program, PathBuf::from("add.lmc"));
+        // The .bin file goes next to the program by default
+        assert_eq!(output, PathBuf::from("add.bin"));
+        assert_eq!(listing, Some(PathBuf::from("add.lst")));
+        assert_eq!(architecture, Architecture::CLASSIC);
+        assert!(parse_args(args("assemble")).is_err());
+    }
+
+    #[test]
+    fn parses_run_options() {
+        let Ok(Command::Run {
+            program,
+            args: parsed,
+        }) = parse_args(args("run add.lmc --address-digits 3 --quiet"))
+        else {
+            panic!("expected the run command");
+        };
+        assert_eq!(program, PathBuf::from("add.lmc"));
+        assert_eq!(parsed.config.architecture, Architecture::new(3).unwrap());
+        assert!(parsed.config.quiet);
+        assert!(parse_args(args("run")).is_err());
+        assert!(parse_args(args("run add.lmc more.lmc")).is_err());
+    }
+
+    #[test]
+    fn parses_create_bin_options() {
+        let Ok(Command::CreateBin {
+            text_file,
+            output,
+            architecture,
+        }) = parse_args(args(
+            "create-bin --from cells.txt -o cells.bin --address-digits 1",
+        ))
+        else {
+            panic!("expected the create-bin command");
+        };
+        assert_eq!(text_file, Some(PathBuf::from("cells.txt")));
+        assert_eq!(output, PathBuf::from("cells.bin"));
+        assert_eq!(architecture, Architecture::new(1).unwrap());
+        assert!(parse_args(args("create-bin --from cells.txt")).is_err());
+    }
 }
@@ -0,0 +1,275 @@
+use std::io::{self, Write};
+
+use crate::{
+    disassembler::disassemble_cell_for, pad_value, Architecture, Computer, MachineState, RunOutcome,
+};
+
+const HELP: &str = "Commands:
+  step [count]          Execute one instruction, or `count` instructions
+  run                   Run until the program halts or reaches a breakpoint
+  reg                   Show the registers
+  mem <address>         Show the contents of a memory address
+  out                   Show everything the program has output so far
+  set acc <value>       Set the accumulator
+  set pc <address>      Set the program counter
+  set <address> <value> Set the contents of a memory address
+  break <address>       Pause `run` before the instruction at an address
+  unbreak <address>     Remove a breakpoint
+  reset                 Put the computer back to how it was when the monitor started
+  help                  Show this list
+  quit                  Leave the monitor";
+
+fn parse_address(text: Option<&str>, architecture: Architecture) -> Result<usize, String> {
+    let text = text.ok_or("Expected an address")?;
+    let last_address = architecture.memory_size() - 1;
+    text.parse()
+        .ok()
+        .filter(|&address| address <= last_address)
+        .ok_or(format!(
+            "'{}' is not an address from 0 to {}",
+            text, last_address
+        ))
+}
+
+fn parse_value(text: Option<&str>, architecture: Architecture) -> Result<i16, String> {
+    let text = text.ok_or("Expected a value")?;
+    let max_value = architecture.max_value();
+    text.parse()
+        .ok()
+        .filter(|value| architecture.value_range().contains(value))
+        .ok_or(format!(
+            "'{}' is not a whole number from -{} to {}",
+            text, max_value, max_value
+        ))
+}
+
+fn print_registers(state: &MachineState) {
+    let registers = state.registers;
+    println!(
+        "PC: {:02}, Instruction: {}, Addr: {:02}, Acc: {}",
+        registers.program_counter,
+        pad_value(registers.instruction_register, 3, false),
+        registers.address_register,
+        pad_value(registers.accumulator, 3, false)
+    );
+}
+
+fn step(computer: &mut Computer, count: usize) {
+    let architecture = computer.architecture();
+    for _ in 0..count {
+        let state = computer.snapshot();
+        let address = state.registers.program_counter;
+        // Show the instruction before running it, in case it changes its own cell
+        if let Some(&cell) = state.ram.get(address) {
+            println!(
+                "{:02}: {}",
+                address,
+                disassemble_cell_for(cell, architecture)
+            );
+        }
+        match computer.step() {
+            Ok(result) if result.halted => {
+                println!("Halted");
+                return;
+            }
+            Ok(_) => {}
+            Err(error) => {
+                println!("{}", error);
+                return;
+            }
+        }
+    }
+    print_registers(&computer.snapshot());
+}
+
+/// Runs a single command, returning `false` if the monitor should stop
+fn run_command(computer: &mut Computer, initial_state: &MachineState, line: &str) -> bool {
+    let architecture = computer.architecture();
+    let mut words = line.split_whitespace();
+    let Some(command) = words.next() else {
+        return true;
+    };
+    let result = match command {
+        "step" | "s" => words
+            .next()
+            .map_or(Ok(1), |count| {
+                count
+                    .parse()
+                    .map_err(|_| format!("'{}' is not a number of steps", count))
+            })
+            .map(|count| step(computer, count)),
+        "run" | "r" => {
+            match computer.run() {
+                Ok(RunOutcome::Halted) => println!("Halted"),
+                Ok(RunOutcome::BreakpointHit(address)) => {
+                    println!("Stopped at breakpoint {:02}", address)
+                }
+                Ok(RunOutcome::CycleLimitReached) => println!("Cycle limit reached"),
+                // Only run_until stops for a condition
+                Ok(RunOutcome::ConditionMet) => {}
+                // run() has already printed the error
+                Err(_) => {}
+            }
+            Ok(())
+        }
+        "reg" => {
+            print_registers(&computer.snapshot());
+            Ok(())
+        }
+        "mem" => parse_address(words.next(), architecture).map(|address| {
+            let cell = computer.snapshot().ram[address];
+            println!(
+                "{:02}: {} ({})",
+                address,
+                pad_value(cell, 3, false),
+                disassemble_cell_for(cell, architecture)
+            );
+        }),
+        "out" => {
+            println!("{}", computer.snapshot().output);
+            Ok(())
+        }
+        "set" => match words.next() {
+            Some("acc") => {
+                parse_value(words.next(), architecture).map(|value| computer.set_accumulator(value))
+            }
+            Some("pc") => parse_address(words.next(), architecture)
+                .map(|address| computer.set_program_counter(address)),
+            target => parse_address(target, architecture).and_then(|address| {
+                parse_value(words.next(), architecture)
+                    .map(|value| computer.set_cell(address, value))
+            }),
+        },
+        "break" => parse_address(words.next(), architecture)
+            .map(|address| computer.add_breakpoint(address)),
+        "unbreak" => parse_address(words.next(), architecture)
+            .map(|address| computer.remove_breakpoint(address)),
+        "reset" => {
+            computer.reset();
+            computer.restore(initial_state.clone());
+            Ok(())
+        }
+        "help" => {
+            println!("{}", HELP);
+            Ok(())
+        }
+        "quit" | "exit" => return false,
+        _ => Err(format!(
+            "Unknown command '{}'. Type 'help' for a list of commands",
+            command
+        )),
+    };
+    if let Err(message) = result {
+        println!("{}", message);
+    }
+    true
+}
+
+/// Reads commands from stdin and runs them on the computer, until `quit` or the end of stdin
+pub fn run(computer: &mut Computer) -> io::Result<()> {
+    let initial_state = computer.snapshot();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+        // Lines are read one at a time without locking stdin, so that INP can read from it too
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        if !run_command(computer, &initial_state, &line) {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{output::OutputEvent, ComputerConfig, InputSource};
+
+    fn computer(source: &str) -> Computer {
+        let config = ComputerConfig {
+            quiet: true,
+            input_source: InputSource::Vector(Vec::new()),
+            ..ComputerConfig::default()
+        };
+        Computer::from_assembly(source, config).unwrap()
+    }
+
+    #[test]
+    fn parses_addresses_and_values() {
+        let architecture = Architecture::CLASSIC;
+        assert_eq!(parse_address(Some("99"), architecture), Ok(99));
+        assert_eq!(
+            parse_address(Some("100"), architecture),
+            Err("'100' is not an address from 0 to 99".to_string())
+        );
+        assert_eq!(
+            parse_address(None, architecture),
+            Err("Expected an address".to_string())
+        );
+        assert_eq!(parse_value(Some("-999"), architecture), Ok(-999));
+        assert_eq!(
+            parse_value(Some("1000"), architecture),
+            Err("'1000' is not a whole number from -999 to 999".to_string())
+        );
+        let architecture = Architecture::new(3).unwrap();
+        assert_eq!(parse_address(Some("999"), architecture), Ok(999));
+        assert_eq!(parse_value(Some("9999"), architecture), Ok(9999));
+    }
+
+    #[test]
+    fn set_commands_change_the_computer() {
+        let mut computer = computer("OUT\nLDA 9\nOUT\nHLT");
+        let initial_state = computer.snapshot();
+        assert!(run_command(&mut computer, &initial_state, "set acc -5"));
+        assert_eq!(computer.accumulator(), -5);
+        assert!(computer.negative_flag());
+        run_command(&mut computer, &initial_state, "set 9 42");
+        run_command(&mut computer, &initial_state, "set pc 1");
+        assert_eq!(computer.program_counter(), 1);
+        run_command(&mut computer, &initial_state, "run");
+        assert_eq!(computer.output_events(), [OutputEvent::Int(42)]);
+    }
+
+    #[test]
+    fn bad_commands_leave_the_computer_alone() {
+        let mut computer = computer("OUT\nHLT");
+        let initial_state = computer.snapshot();
+        for command in [
+            "set acc 1000",
+            "set pc 100",
+            "set 5",
+            "step x",
+            "frobnicate",
+        ] {
+            assert!(run_command(&mut computer, &initial_state, command));
+        }
+        assert_eq!(computer.snapshot(), initial_state);
+    }
+
+    #[test]
+    fn breakpoints_and_reset() {
+        let mut computer = computer("OUT\nOUT\nHLT");
+        let initial_state = computer.snapshot();
+        run_command(&mut computer, &initial_state, "break 1");
+        run_command(&mut computer, &initial_state, "run");
+        assert_eq!(computer.program_counter(), 1);
+        run_command(&mut computer, &initial_state, "step 2");
+        assert_eq!(computer.output_events().len(), 2);
+        run_command(&mut computer, &initial_state, "reset");
+        assert_eq!(computer.snapshot(), initial_state);
+        run_command(&mut computer, &initial_state, "unbreak 1");
+        run_command(&mut computer, &initial_state, "run");
+        assert_eq!(computer.output_events().len(), 2);
+    }
+
+    #[test]
+    fn quit_stops_the_monitor() {
+        let mut computer = computer("HLT");
+        let initial_state = computer.snapshot();
+        assert!(!run_command(&mut computer, &initial_state, "quit"));
+        assert!(!run_command(&mut computer, &initial_state, "exit"));
+        assert!(run_command(&mut computer, &initial_state, "   "));
+    }
+}
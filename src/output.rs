@@ -0,0 +1,235 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+/// What to put between two numbers that are output one after the other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberSeparator {
+    Newline,
+    Space,
+    /// Join the numbers together, e.g. 1 then 2 is output as `12`
+    #[default]
+    None,
+}
+
+impl NumberSeparator {
+    fn as_str(self) -> &'static str {
+        match self {
+            NumberSeparator::Newline => "\n",
+            NumberSeparator::Space => " ",
+            NumberSeparator::None => "",
+        }
+    }
+}
+
+/// A single character or number that the program output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputEvent {
+    Char(char),
+    Int(i16),
+}
+
+/// Everything the program has output so far, optionally also sent to a writer as it is produced
+#[derive(Default)]
+pub struct Output {
+    buffer: String,
+    /// Each character and number in the order they were output, which the buffer can't tell apart
+    events: Vec<OutputEvent>,
+    writer: Option<Box<dyn Write>>,
+    number_separator: NumberSeparator,
+    /// Whether the last thing output was a number, so the next number needs a separator
+    last_was_number: bool,
+    listener: Option<Box<dyn FnMut(OutputEvent)>>,
+}
+
+impl Output {
+    pub fn new() -> Self {
+        Output::default()
+    }
+
+    /// Creates an `Output` that also writes each new piece of output to `writer` straight away
+    pub fn with_writer(writer: impl Write + 'static) -> Self {
+        Output {
+            writer: Some(Box::new(writer)),
+            ..Output::default()
+        }
+    }
+
+    /// Sets what goes between consecutive numbers from `push_int`, e.g. `Space` to output `1 2 3`
+    pub fn with_number_separator(self, number_separator: NumberSeparator) -> Self {
+        Output {
+            number_separator,
+            ..self
+        }
+    }
+
+    /// Calls `listener` with each new piece of output, as well as storing it
+    pub(crate) fn set_listener(&mut self, listener: impl FnMut(OutputEvent) + 'static) {
+        self.listener = Some(Box::new(listener));
+    }
+
+    fn record(&mut self, event: OutputEvent) {
+        self.events.push(event);
+        if let Some(listener) = &mut self.listener {
+            listener(event);
+        }
+    }
+
+    fn push_str(&mut self, text: &str) -> io::Result<()> {
+        self.buffer.push_str(text);
+        self.write_from(self.buffer.len() - text.len())
+    }
+
+    /// Sends everything in the buffer from `start` onwards to the writer
+    fn write_from(&mut self, start: usize) -> io::Result<()> {
+        if let Some(writer) = &mut self.writer {
+            writer.write_all(&self.buffer.as_bytes()[start..])?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn push_char(&mut self, character: char) -> io::Result<()> {
+        self.last_was_number = false;
+        self.record(OutputEvent::Char(character));
+        self.push_str(character.encode_utf8(&mut [0; 4]))
+    }
+
+    pub fn push_int(&mut self, integer: i16) -> io::Result<()> {
+        let separator = if self.last_was_number {
+            self.number_separator.as_str()
+        } else {
+            ""
+        };
+        self.last_was_number = true;
+        self.record(OutputEvent::Int(integer));
+        // Format straight into the buffer, so that outputting a number doesn't allocate
+        let start = self.buffer.len();
+        self.buffer.push_str(separator);
+        write!(self.buffer, "{}", integer).expect("writing to a String can't fail");
+        self.write_from(start)
+    }
+
+    pub fn read_all(&self) -> &str {
+        &self.buffer
+    }
+
+    /// Everything that has been output so far, e.g. `[Int(42), Char('!')]` rather than `42!`
+    pub fn events(&self) -> &[OutputEvent] {
+        &self.events
+    }
+
+    /// Replaces everything that has been output so far, without sending it to the writer.
+    /// The events are cleared, because they can't be worked out from the text.
+    pub(crate) fn replace_contents(&mut self, contents: String) {
+        self.buffer = contents;
+        self.events.clear();
+        self.last_was_number = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::SharedWriter;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn numbers_are_joined_by_default() {
+        let mut output = Output::new();
+        output.push_int(1).unwrap();
+        output.push_int(-2).unwrap();
+        output.push_char('!').unwrap();
+        assert_eq!(output.read_all(), "1-2!");
+    }
+
+    #[test]
+    fn separator_only_goes_between_numbers() {
+        let mut output = Output::new().with_number_separator(NumberSeparator::Space);
+        output.push_int(1).unwrap();
+        output.push_int(2).unwrap();
+        output.push_char('a').unwrap();
+        output.push_int(3).unwrap();
+        output.push_int(4).unwrap();
+        assert_eq!(output.read_all(), "1 2a3 4");
+        let mut output = Output::new().with_number_separator(NumberSeparator::Space);
+        for integer in 1..=3 {
+            output.push_int(integer).unwrap();
+        }
+        assert_eq!(output.read_all(), "1 2 3");
+        let mut output = Output::new().with_number_separator(NumberSeparator::Newline);
+        output.push_int(5).unwrap();
+        output.push_int(6).unwrap();
+        assert_eq!(output.read_all(), "5\n6");
+    }
+
+    #[test]
+    fn thousands_of_numbers() {
+        let mut output = Output::new().with_number_separator(NumberSeparator::Newline);
+        for integer in 0..5000 {
+            output.push_int(integer % 1000).unwrap();
+        }
+        assert_eq!(output.events().len(), 5000);
+        assert_eq!(output.read_all().lines().count(), 5000);
+        assert!(output.read_all().ends_with("998\n999"));
+    }
+
+    #[test]
+    fn events_keep_chars_and_numbers_apart() {
+        let mut output = Output::new();
+        output.push_int(42).unwrap();
+        output.push_char('4').unwrap();
+        output.push_char('é').unwrap();
+        assert_eq!(
+            output.events(),
+            [
+                OutputEvent::Int(42),
+                OutputEvent::Char('4'),
+                OutputEvent::Char('é')
+            ]
+        );
+    }
+
+    #[test]
+    fn writer_gets_each_piece_of_output() {
+        let writer = SharedWriter::default();
+        let mut output =
+            Output::with_writer(writer.clone()).with_number_separator(NumberSeparator::Space);
+        output.push_int(7).unwrap();
+        assert_eq!(*writer.0.borrow(), b"7");
+        output.push_int(8).unwrap();
+        output.push_char('é').unwrap();
+        assert_eq!(writer.text(), "7 8é");
+    }
+
+    #[test]
+    fn listener_is_called_for_each_event() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut output = Output::new();
+        let listener_events = Rc::clone(&events);
+        output.set_listener(move |event| listener_events.borrow_mut().push(event));
+        output.push_char('a').unwrap();
+        output.push_int(1).unwrap();
+        assert_eq!(
+            *events.borrow(),
+            [OutputEvent::Char('a'), OutputEvent::Int(1)]
+        );
+    }
+
+    #[test]
+    fn replacing_contents_clears_events() {
+        let writer = SharedWriter::default();
+        let mut output =
+            Output::with_writer(writer.clone()).with_number_separator(NumberSeparator::Space);
+        output.push_int(1).unwrap();
+        output.replace_contents("restored".to_string());
+        assert_eq!(output.read_all(), "restored");
+        assert!(output.events().is_empty());
+        // The restored text isn't written again
+        assert_eq!(*writer.0.borrow(), b"1");
+        // The next number doesn't get a separator, because the last output wasn't a number
+        output.push_int(2).unwrap();
+        assert_eq!(output.read_all(), "restored2");
+    }
+}
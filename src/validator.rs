@@ -0,0 +1,194 @@
+use std::fmt;
+
+use crate::{
+    instruction::{decode_for, Instruction},
+    Architecture,
+};
+
+/// Something suspicious about a program, found without running it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationWarning {
+    /// A cell that can be reached as an instruction isn't a valid instruction
+    IllegalInstruction { address: usize, cell: i16 },
+    /// A branch goes to an address after the end of the program
+    BranchOutsideProgram { address: usize, target: usize },
+    /// The last instruction of the program can be run, and isn't a branch or `HLT`
+    RunsOffEnd { address: usize },
+    /// None of the instructions that can be reached are `HLT`
+    NoReachableHalt,
+}
+
+impl fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationWarning::IllegalInstruction { address, cell } => {
+                write!(
+                    f,
+                    "Address {:02}: {} is not a valid instruction",
+                    address, cell
+                )
+            }
+            ValidationWarning::BranchOutsideProgram { address, target } => {
+                write!(
+                    f,
+                    "Address {:02}: Branches to address {:02}, which is after the end of the program",
+                    address, target
+                )
+            }
+            ValidationWarning::RunsOffEnd { address } => {
+                write!(
+                    f,
+                    "Address {:02}: Execution carries on past the end of the program",
+                    address
+                )
+            }
+            ValidationWarning::NoReachableHalt => write!(f, "The program never halts"),
+        }
+    }
+}
+
+/// Checks a program for obvious mistakes by following every path it could take from
+/// `start_address`.
+/// Cells that can't be reached aren't checked, because they could be data.
+pub fn validate(
+    cells: &[i16],
+    start_address: usize,
+    architecture: Architecture,
+) -> Vec<ValidationWarning> {
+    let program_length = cells.len();
+    if start_address >= program_length {
+        return Vec::new();
+    }
+
+    let mut warnings = Vec::new();
+    let mut visited = vec![false; program_length];
+    let mut to_visit = vec![start_address];
+    let mut halt_reachable = false;
+    while let Some(address) = to_visit.pop() {
+        if visited[address] {
+            continue;
+        }
+        visited[address] = true;
+
+        let instruction = match decode_for(cells[address], architecture) {
+            Ok(instruction) => instruction,
+            Err(error) => {
+                warnings.push(ValidationWarning::IllegalInstruction {
+                    address,
+                    cell: error.cell,
+                });
+                continue;
+            }
+        };
+        let (branch_target, falls_through) = match instruction {
+            Instruction::Halt => {
+                halt_reachable = true;
+                (None, false)
+            }
+            Instruction::Branch(target) => (Some(target), false),
+            Instruction::BranchIfZero(target) | Instruction::BranchIfPositive(target) => {
+                (Some(target), true)
+            }
+            _ => (None, true),
+        };
+
+        if let Some(target) = branch_target {
+            if target < program_length {
+                to_visit.push(target);
+            } else {
+                warnings.push(ValidationWarning::BranchOutsideProgram { address, target });
+            }
+        }
+        if falls_through {
+            if address + 1 < program_length {
+                to_visit.push(address + 1);
+            } else {
+                warnings.push(ValidationWarning::RunsOffEnd { address });
+            }
+        }
+    }
+
+    if !halt_reachable {
+        warnings.push(ValidationWarning::NoReachableHalt);
+    }
+    // Report problems in the order they appear in the program
+    warnings.sort_by_key(|warning| match warning {
+        ValidationWarning::IllegalInstruction { address, .. }
+        | ValidationWarning::BranchOutsideProgram { address, .. }
+        | ValidationWarning::RunsOffEnd { address } => *address,
+        ValidationWarning::NoReachableHalt => usize::MAX,
+    });
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+
+    fn validate_assembly(source: &str) -> Vec<ValidationWarning> {
+        validate(&assemble(source).unwrap(), 0, Architecture::CLASSIC)
+    }
+
+    #[test]
+    fn valid_program_has_no_warnings() {
+        // The data after HLT would be an illegal instruction, but it's never run
+        let source = "loop INP\nBRZ done\nOUT\nBRA loop\ndone HLT\nDAT 400";
+        assert_eq!(validate_assembly(source), []);
+    }
+
+    #[test]
+    fn reports_reachable_problems_in_order() {
+        let source = "INP\nBRZ 50\nDAT 400\nHLT";
+        assert_eq!(
+            validate_assembly(source),
+            [
+                ValidationWarning::BranchOutsideProgram {
+                    address: 1,
+                    target: 50
+                },
+                ValidationWarning::IllegalInstruction {
+                    address: 2,
+                    cell: 400
+                },
+                ValidationWarning::NoReachableHalt,
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_running_off_the_end() {
+        assert_eq!(
+            validate_assembly("INP\nBRZ 4\nHLT\nDAT 0\nOUT"),
+            [ValidationWarning::RunsOffEnd { address: 4 }]
+        );
+        assert_eq!(
+            validate_assembly("INP\nOUT"),
+            [
+                ValidationWarning::RunsOffEnd { address: 1 },
+                ValidationWarning::NoReachableHalt
+            ]
+        );
+    }
+
+    #[test]
+    fn branch_into_empty_ram_and_missing_halt() {
+        assert_eq!(
+            validate(&[901, 642], 0, Architecture::CLASSIC),
+            [
+                ValidationWarning::BranchOutsideProgram {
+                    address: 1,
+                    target: 42
+                },
+                ValidationWarning::NoReachableHalt,
+            ]
+        );
+    }
+
+    #[test]
+    fn starts_from_start_address() {
+        let cells = assemble("DAT 400\nHLT").unwrap();
+        assert_eq!(validate(&cells, 1, Architecture::CLASSIC), []);
+        assert_eq!(validate(&cells, 5, Architecture::CLASSIC), []);
+    }
+}
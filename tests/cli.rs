@@ -0,0 +1,47 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+/// Runs the main binary with the given arguments, piping `stdin` into it
+fn run(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rusty_man_computer"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn reads_ram_from_stdin() {
+    // LDA 3, OUT, HLT, DAT 42
+    let bytes = [0x01, 0xF7, 0x03, 0x86, 0x00, 0x00, 0x00, 0x2A];
+    let output = run(&["-", "--quiet"], &bytes);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"42");
+
+    // With nothing piped in, RAM stays empty so the program halts straight away
+    let output = run(&["-", "--quiet"], &[]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"");
+}
+
+#[test]
+fn quiet_mode_only_prints_program_output() {
+    // LDA 4, OUT, OTC, HLT, DAT 65, and an extra byte that would normally cause a warning
+    let bytes = [
+        0x01, 0xF8, 0x03, 0x86, 0x03, 0x9A, 0x00, 0x00, 0x00, 0x41, 0x07,
+    ];
+    let output = run(&["-", "--quiet"], &bytes);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"65A");
+    assert_eq!(output.stderr, b"");
+
+    // Without --quiet there's a lot more
+    let output = run(&["-"], &bytes);
+    assert!(output.stdout.len() > 100);
+}